@@ -0,0 +1,464 @@
+use std::thread;
+use std::time::Instant;
+
+use image::RgbImage;
+use strum_macros::{Display, EnumString};
+
+use crate::generator::painter::shape::Shape;
+use crate::generator::utils::benchmark::TimerBenchmark;
+use crate::generator::utils::diff::DiffMap;
+use crate::generator::utils::patch::Patch;
+use crate::generator::utils::random::rng::Rng;
+use crate::generator::utils::random::{get_pick_rng, get_random_index_weighted};
+use crate::generator::utils::terminal;
+use crate::generator::{Generator, Painters, ProcessCallback, ProcessCallbackResult};
+
+// Number of individuals competing for each parent pick
+const TOURNAMENT_SIZE: usize = 3;
+
+/// How the generator searches for an image close to the target.
+#[derive(Clone, Copy, Debug, Default, Display, EnumString, PartialEq)]
+pub enum Optimizer {
+	#[default]
+	#[strum(serialize = "incremental")]
+	Incremental,
+	#[strum(serialize = "genetic")]
+	Genetic,
+}
+
+/// One element of an individual: a shape, and the painter that picked it.
+#[derive(Clone)]
+struct Gene {
+	painter_index: usize,
+	shape: Shape,
+}
+
+/// A candidate image, as the list of elements painted over the starting image, in order.
+#[derive(Clone)]
+struct Individual {
+	genes: Vec<Gene>,
+	diff: f64,
+}
+
+impl Generator {
+	/// Runs a genetic algorithm generation process. Instead of painting over the current image one element
+	/// at a time, a population of lists of `num_shapes` elements is evolved: on each try, new individuals are
+	/// bred from the best ones, by crossing over their lists and mutating (changing or replacing) some of
+	/// their elements. The best individual is always kept, and the current image is its rendering over the
+	/// starting image; tries that improve it count as generations.
+	#[allow(clippy::too_many_arguments)]
+	pub fn evolve(
+		&mut self,
+		target_tries: u32,
+		target_generations: u32,
+		target_diff: f64,
+		threads: usize,
+		population_size: usize,
+		num_shapes: usize,
+		mutation_rate: f64,
+		painters: Painters,
+		rng_seed: u32,
+		cb: Option<ProcessCallback>,
+	) {
+		let background = self.current.clone();
+		// Generations improve whole lists of elements, so they aren't won by any single painter
+		self.painter_runs.clear();
+		let diff_map =
+			DiffMap::new(&background, &self.target, self.diff_metric, self.importance_map.as_ref());
+		let population_size = population_size.max(2);
+
+		let mut rng = Rng::from_seed(rng_seed);
+		let painter_rng_seed = rng.next();
+		let mut iteration: u32 = 0;
+
+		println!("Creating initial population of {} individuals...", population_size);
+
+		let mut timer = TimerBenchmark::new();
+		timer.start();
+
+		let genes: Vec<Vec<Gene>> = (0..population_size)
+			.map(|_| {
+				(0..num_shapes)
+					.map(|_| new_gene(&painters, &background, &self.target, painter_rng_seed, &mut iteration))
+					.collect()
+			})
+			.collect();
+		let diffs = self.evaluate(&painters, &background, &diff_map, &genes, threads);
+		let mut population: Vec<Individual> = genes
+			.into_iter()
+			.zip(diffs)
+			.map(|(genes, diff)| Individual {
+				genes,
+				diff,
+			})
+			.collect();
+		sort_population(&mut population);
+		let mut curr_diff = population[0].diff;
+		self.current = self.render(&painters, &background, &population[0].genes);
+
+		println!("Starting tries; initial difference from target is {:.2}%.", curr_diff * 100.0);
+		println!("First try...");
+
+		let mut curr_tries: u32 = 0;
+		let mut curr_generations: u32 = 0;
+		let mut time_last_print = Instant::now();
+
+		loop {
+			// Breed the new population, always keeping the best individual
+			let children: Vec<Vec<Gene>> = (1..population_size)
+				.map(|_| {
+					let parent_1 = pick_parent(&population, &mut rng);
+					let parent_2 = pick_parent(&population, &mut rng);
+					let mut genes = crossover(&parent_1.genes, &parent_2.genes, &mut rng);
+					for gene in genes.iter_mut() {
+						if rng.next_f64() < mutation_rate {
+							if rng.next_bool() {
+								gene.shape.mutate(&mut rng);
+							} else {
+								*gene = new_gene(
+									&painters,
+									&background,
+									&self.target,
+									painter_rng_seed,
+									&mut iteration,
+								);
+							}
+						}
+					}
+					genes
+				})
+				.collect();
+			let diffs = self.evaluate(&painters, &background, &diff_map, &children, threads);
+			population.truncate(1);
+			population.extend(children.into_iter().zip(diffs).map(|(genes, diff)| Individual {
+				genes,
+				diff,
+			}));
+			sort_population(&mut population);
+
+			let used = population[0].diff < curr_diff;
+			if used {
+				curr_diff = population[0].diff;
+				curr_generations += 1;
+				self.current = self.render(&painters, &background, &population[0].genes);
+			}
+
+			curr_tries += 1;
+
+			let finished = (target_tries > 0 && curr_tries == target_tries)
+				|| (target_generations > 0 && curr_generations == target_generations)
+				|| (target_diff > 0.0 && curr_diff <= target_diff);
+
+			if let Some(process_callback) = cb {
+				let mut painter_elements = vec![0u32; painters.len()];
+				for gene in &population[0].genes {
+					painter_elements[gene.painter_index] += 1;
+				}
+				process_callback(
+					self,
+					ProcessCallbackResult {
+						is_success: used,
+						is_final: finished,
+						num_tries: curr_tries,
+						num_generations: curr_generations,
+						diff: curr_diff,
+						time_elapsed: timer.current_ms() as f32 / 1000.0,
+						metadata: vec![painters.iter().map(|painter| painter.value.get_metadata()).collect()],
+						painter_generations: vec![],
+						painter_elements,
+						temperature: 0.0,
+						state: None,
+					},
+				);
+			}
+
+			// Only output log if the generation succeeded, or if enough time has passed
+			if used || time_last_print.elapsed().as_secs() >= 1 {
+				terminal::cursor_up();
+				terminal::erase_line_to_end();
+				if target_tries > 0 {
+					print!("Try {}/{}: ", curr_tries, target_tries);
+				} else {
+					print!("Try {}: ", curr_tries);
+				}
+				if target_generations > 0 {
+					print!("{}/{} generations so far, ", curr_generations, target_generations);
+				} else {
+					print!("{} generations so far, ", curr_generations);
+				}
+				println!("new difference is {:.2}%", curr_diff * 100.0);
+				time_last_print = Instant::now();
+			}
+
+			if finished {
+				// Requirements reached, can stop trying
+				break;
+			}
+		}
+
+		timer.stop();
+
+		println!(
+			"Finished {} tries in {:.3}s ({:.3}ms avg per try), with a population of {} individuals of {} elements.",
+			curr_tries,
+			timer.last_ms() / 1000.0,
+			timer.last_ms() / curr_tries as f64,
+			population_size,
+			num_shapes
+		);
+		println!(
+			"Produced {} generations, a {:.2}% success rate.",
+			curr_generations,
+			curr_generations as f64 / curr_tries as f64 * 100.0
+		);
+		println!("The final difference from target is {:.2}%.", curr_diff * 100.0);
+	}
+
+	/// Paints a list of elements over the starting image, in order.
+	fn render(&self, painters: &Painters, background: &RgbImage, genes: &[Gene]) -> RgbImage {
+		let mut canvas = background.clone();
+		for gene in genes {
+			let mut patch =
+				painters[gene.painter_index].value.paint_shape(&canvas, &gene.shape).expect("painting");
+			if let Some(paint_mask) = self.paint_mask.as_ref() {
+				patch.mask(&canvas, paint_mask);
+			}
+			patch.apply(&mut canvas);
+		}
+		canvas
+	}
+
+	/// Finds the difference from the target of each individual, spread over a number of threads.
+	fn evaluate(
+		&self,
+		painters: &Painters,
+		background: &RgbImage,
+		diff_map: &DiffMap,
+		individuals: &[Vec<Gene>],
+		threads: usize,
+	) -> Vec<f64> {
+		let get_diff = |genes: &Vec<Gene>| {
+			// The whole image is diffed as a patch over the starting image, so the target is only prepared once
+			let patch = Patch {
+				x: 0,
+				y: 0,
+				image: self.render(painters, background, genes),
+			};
+			diff_map.get_patch_diff(&patch, &self.target)
+		};
+		if threads <= 1 {
+			return individuals.iter().map(get_diff).collect();
+		}
+		let chunk_size = individuals.len().div_ceil(threads).max(1);
+		thread::scope(|scope| {
+			let handles: Vec<_> = individuals
+				.chunks(chunk_size)
+				.map(|chunk| scope.spawn(move || chunk.iter().map(get_diff).collect::<Vec<f64>>()))
+				.collect();
+			handles.into_iter().flat_map(|handle| handle.join().expect("joining evaluation thread")).collect()
+		})
+	}
+}
+
+/// Creates a new random element, picking a painter the same way candidates are picked by `process()`.
+fn new_gene(
+	painters: &Painters,
+	background: &RgbImage,
+	target: &RgbImage,
+	painter_rng_seed: u32,
+	iteration: &mut u32,
+) -> Gene {
+	let painter_index = if painters.len() == 1 {
+		0
+	} else {
+		get_random_index_weighted(&mut get_pick_rng(painter_rng_seed, *iteration), painters)
+	};
+	let shape =
+		painters[painter_index].value.get_shape(background, *iteration, target, None).expect("painting");
+	*iteration = iteration.wrapping_add(1);
+	Gene {
+		painter_index,
+		shape,
+	}
+}
+
+/// Sorts a population from the best to the worst individual; ties keep their order.
+fn sort_population(population: &mut [Individual]) {
+	population.sort_by(|a, b| a.diff.total_cmp(&b.diff));
+}
+
+/// Picks the best of a few random individuals.
+fn pick_parent<'a>(population: &'a [Individual], rng: &mut Rng) -> &'a Individual {
+	(0..TOURNAMENT_SIZE)
+		.map(|_| rng.next_u32_range(0, population.len() as u32).min(population.len() as u32 - 1) as usize)
+		.min()
+		.map(|index| &population[index])
+		.expect("picking a parent")
+}
+
+/// Creates a list of elements with the start of one parent's list, and the end of the other's.
+fn crossover(genes_1: &[Gene], genes_2: &[Gene], rng: &mut Rng) -> Vec<Gene> {
+	let point = rng.next_u32_range(0, genes_1.len() as u32 + 1).min(genes_1.len() as u32) as usize;
+	genes_1[..point].iter().chain(genes_2[point..].iter()).cloned().collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use std::cell::RefCell;
+
+	use image::{DynamicImage, Rgb};
+
+	use super::*;
+	use crate::generator::painter::rect::RectPainter;
+	use crate::generator::painter::shape::ShapeDetails;
+	use crate::generator::painter::Painter;
+	use crate::generator::utils::color::BlendingMode;
+	use crate::generator::utils::fill::Fill;
+	use crate::generator::utils::units::{Rectangle, WeightedValue};
+
+	thread_local! {
+		static REPORTED_DIFFS: RefCell<Vec<f64>> = const { RefCell::new(vec![]) };
+	}
+
+	/// Creates a list of elements that can be told apart by their painter and their position.
+	fn get_genes(painter_index: usize, length: usize) -> Vec<Gene> {
+		(0..length)
+			.map(|i| Gene {
+				painter_index,
+				shape: Shape::new(
+					i as f64,
+					0.0,
+					1.0,
+					BlendingMode::default(),
+					Fill::Flat([0, 0, 0]),
+					Rectangle {
+						x: 0,
+						y: 0,
+						width: 10,
+						height: 10,
+					},
+					&Rectangle {
+						x: i as f64 - 1.0,
+						y: -1.0,
+						width: 2.0,
+						height: 2.0,
+					},
+					ShapeDetails::Circles {
+						radius: 1.0,
+					},
+				),
+			})
+			.collect()
+	}
+
+	fn get_population(diffs: &[f64]) -> Vec<Individual> {
+		diffs
+			.iter()
+			.enumerate()
+			.map(|(i, &diff)| Individual {
+				genes: get_genes(i, 1),
+				diff,
+			})
+			.collect()
+	}
+
+	#[test]
+	fn test_crossover() {
+		let genes_1 = get_genes(1, 8);
+		let genes_2 = get_genes(2, 8);
+		let mut rng = Rng::from_seed(1);
+		for _ in 0..100 {
+			let genes = crossover(&genes_1, &genes_2, &mut rng);
+			assert_eq!(genes.len(), 8);
+			let point = genes.iter().position(|gene| gene.painter_index == 2).unwrap_or(genes.len());
+			for (i, gene) in genes.iter().enumerate() {
+				assert_eq!(
+					gene.painter_index,
+					if i < point {
+						1
+					} else {
+						2
+					}
+				);
+				assert_eq!(gene.shape.x, i as f64);
+			}
+		}
+	}
+
+	#[test]
+	fn test_pick_parent() {
+		let population = get_population(&[0.0; 10]);
+		let mut rng = Rng::from_seed(1);
+		let mut counts = [0u32; 10];
+		for _ in 0..1000 {
+			let parent = pick_parent(&population, &mut rng);
+			let index = population.iter().position(|individual| std::ptr::eq(individual, parent));
+			counts[index.expect("parent from population")] += 1;
+		}
+		assert_eq!(counts.iter().sum::<u32>(), 1000);
+		assert!(counts[0] > counts[4]);
+		assert!(counts[4] > counts[9]);
+	}
+
+	#[test]
+	fn test_sort_population() {
+		let mut population = get_population(&[0.5, 0.2, 0.5, 0.1, 0.2]);
+		sort_population(&mut population);
+		let diffs: Vec<f64> = population.iter().map(|individual| individual.diff).collect();
+		let indices: Vec<usize> =
+			population.iter().map(|individual| individual.genes[0].painter_index).collect();
+		assert_eq!(diffs, vec![0.1, 0.2, 0.2, 0.5, 0.5]);
+		assert_eq!(indices, vec![3, 1, 4, 0, 2]);
+	}
+
+	fn get_painters() -> Painters {
+		let painter: Box<dyn Painter + Send + Sync> = Box::new(RectPainter::new());
+		vec![WeightedValue {
+			value: painter,
+			weight: 1.0,
+		}]
+	}
+
+	#[test]
+	fn test_evolve_keeps_best_individual() {
+		let target = RgbImage::from_fn(16, 16, |x, y| Rgb([(x * 16) as u8, (y * 16) as u8, 128]));
+		let mut generator = Generator::from_image(DynamicImage::ImageRgb8(target), 1.0);
+
+		// Same initial population as the one created by `evolve()`
+		let painters = get_painters();
+		let background = generator.current.clone();
+		let diff_map = DiffMap::new(&background, &generator.target, generator.diff_metric, None);
+		let painter_rng_seed = Rng::from_seed(1).next();
+		let mut iteration: u32 = 0;
+		let genes: Vec<Vec<Gene>> = (0..6)
+			.map(|_| {
+				(0..4)
+					.map(|_| {
+						new_gene(&painters, &background, &generator.target, painter_rng_seed, &mut iteration)
+					})
+					.collect()
+			})
+			.collect();
+		let initial_diff = generator
+			.evaluate(&painters, &background, &diff_map, &genes, 1)
+			.into_iter()
+			.fold(f64::MAX, f64::min);
+
+		fn on_processed(generator: &Generator, result: ProcessCallbackResult) {
+			// The current image is always the rendering of the best individual
+			let diff =
+				DiffMap::new(&generator.current, &generator.target, generator.diff_metric, None).diff();
+			assert!((diff - result.diff).abs() < 1e-9);
+			REPORTED_DIFFS.with(|diffs| diffs.borrow_mut().push(result.diff));
+		}
+
+		generator.evolve(20, 0, 0.0, 1, 6, 4, 0.5, get_painters(), 1, Some(on_processed));
+
+		let diffs = REPORTED_DIFFS.with(|diffs| diffs.borrow().clone());
+		assert_eq!(diffs.len(), 20);
+		assert!(diffs[0] <= initial_diff);
+		for pair in diffs.windows(2) {
+			assert!(pair[1] <= pair[0]);
+		}
+	}
+}
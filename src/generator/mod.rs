@@ -1,25 +1,46 @@
 use std::collections::HashMap;
-use std::sync::{mpsc, Arc};
-use std::thread;
+use std::sync::Arc;
 use std::time::Instant;
 
-use image::{DynamicImage, Rgb, RgbImage};
+use image::imageops::{resize as imageops_resize, FilterType};
+use image::{DynamicImage, GrayImage, Rgb, RgbImage};
 
+use painter::shape::Shape;
 use painter::Painter;
+use utils::acceptance::{Acceptance, Acceptor};
 use utils::benchmark::TimerBenchmark;
+use utils::color::BlendingMode;
+use utils::coloring::{get_optimal_color, ColorMode};
+use utils::diff::{DiffMap, DiffMetric};
+use utils::fill::Fill;
 use utils::formatting::format_time;
-use utils::image::{color_transform as image_color_transform, diff as image_diff, scale as image_scale};
+use utils::image::{color_transform as image_color_transform, resize as image_resize, scale as image_scale};
 use utils::numbers::AverageNumber;
+use utils::patch::Patch;
+use utils::placement::{ErrorMap, Placement};
+use utils::random::rng::Rng;
+use utils::random::{get_pick_rng, get_random_index_weighted, get_rng};
+use utils::regions::{get_label_areas, get_label_bounds, get_labels, RegionWeight};
 use utils::terminal;
+use utils::units::{Rectangle, WeightedValue};
+use workers::{Candidate, CandidateWorkers};
 
+pub mod genetic;
 pub mod painter;
 pub mod utils;
+mod workers;
+
+/// The painters that can be picked for a region, with their weights.
+pub type Painters = Vec<WeightedValue<Box<dyn Painter + Send + Sync>>>;
 
 pub enum ProcessResult {
-	// Image generated and sent along with its diff value
-	Ok(RgbImage, f64),
+	// Patch generated and sent along with the shape painted, its diff value, and the indices of the candidate,
+	// region, and painter used
+	Ok(Patch, Box<Shape>, f64, usize, usize, usize),
 	// Image generated, but we now its diff is not better than the current one, so we don't send anything
 	Ignore,
+	// All candidates sent to a thread were processed, and its references to the current image released
+	Done,
 	// Could not generate image because of an error
 	Error(String),
 }
@@ -31,7 +52,38 @@ pub struct ProcessCallbackResult {
 	pub num_generations: u32,
 	pub diff: f64,
 	pub time_elapsed: f32,
-	pub metadata: HashMap<String, String>,
+	// Metadata of each painter of each region
+	pub metadata: Vec<Vec<HashMap<String, String>>>,
+	// Number of generations won by each painter of each region
+	pub painter_generations: Vec<Vec<u32>>,
+	// Number of elements of the current image painted by each painter, when the image is a list of elements
+	pub painter_elements: Vec<u32>,
+	pub temperature: f64,
+	pub state: Option<ProcessState>,
+}
+
+/// Where a generation process is at after a try, so it can be resumed exactly from there later.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProcessState {
+	pub rng_seed: u32,
+	pub num_tries: u32,
+	pub num_generations: u32,
+	pub num_processes: u32,
+	pub time_elapsed: f64,
+	pub temperature: f64,
+	pub painter_generations: Vec<Vec<u32>>,
+	pub diff_total: f64,
+	pub label_errors: Vec<f64>,
+	// This grows with every generation, so it's only filled in when saved; see `Generator::get_painter_runs()`
+	pub painter_runs: Vec<PainterRun>,
+}
+
+/// A number of consecutive generations won by the same painter of the same region.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PainterRun {
+	pub region: usize,
+	pub painter: usize,
+	pub generations: u32,
 }
 
 pub struct GeneratorBenchmarks {
@@ -49,6 +101,17 @@ type ProcessCallback = fn(generator: &Generator, result: ProcessCallbackResult);
 pub struct Generator {
 	target: RgbImage,
 	current: RgbImage,
+	diff_metric: DiffMetric,
+	importance_map: Option<GrayImage>,
+	paint_mask: Option<GrayImage>,
+	labels: Vec<u16>,
+	region_weight: RegionWeight,
+	placement: Placement,
+	refine_steps: u32,
+	color_mode: ColorMode,
+	acceptor: Acceptor,
+	resume_state: Option<ProcessState>,
+	painter_runs: Vec<PainterRun>,
 }
 
 fn print_benchmark(bench: &TimerBenchmark, label: &str) {
@@ -72,6 +135,17 @@ impl Generator {
 		Generator {
 			target,
 			current,
+			diff_metric: DiffMetric::default(),
+			importance_map: None,
+			paint_mask: None,
+			labels: vec![],
+			region_weight: RegionWeight::default(),
+			placement: Placement::default(),
+			refine_steps: 0,
+			color_mode: ColorMode::default(),
+			acceptor: Acceptor::new(Acceptance::default(), 0.0, 1.0),
+			resume_state: None,
+			painter_runs: vec![],
 		}
 	}
 
@@ -84,6 +158,17 @@ impl Generator {
 		Generator {
 			target: image_color_transform(&target, matrix),
 			current,
+			diff_metric: DiffMetric::default(),
+			importance_map: None,
+			paint_mask: None,
+			labels: vec![],
+			region_weight: RegionWeight::default(),
+			placement: Placement::default(),
+			refine_steps: 0,
+			color_mode: ColorMode::default(),
+			acceptor: Acceptor::new(Acceptance::default(), 0.0, 1.0),
+			resume_state: None,
+			painter_runs: vec![],
 		}
 	}
 
@@ -96,6 +181,83 @@ impl Generator {
 		self.current = RgbImage::from_pixel(dimensions.0, dimensions.1, Rgb([r, g, b]))
 	}
 
+	pub fn set_diff_metric(&mut self, diff_metric: DiffMetric) {
+		self.diff_metric = diff_metric;
+	}
+
+	/// Sets a grayscale map of how much each pixel of the target matters when calculating differences, from
+	/// black (doesn't matter) to white (full weight). It's resized to match the (scaled) target.
+	pub fn set_importance_map(&mut self, importance_map: DynamicImage) {
+		self.importance_map = Some(self.get_target_sized_map(importance_map));
+	}
+
+	/// Sets a grayscale mask of where painting is allowed, from black (never painted) to white (painted
+	/// normally). It's resized to match the (scaled) target.
+	pub fn set_paint_mask(&mut self, paint_mask: DynamicImage) {
+		self.paint_mask = Some(self.get_target_sized_map(paint_mask));
+	}
+
+	/// Sets a color-coded map of regions, each painted with their own painters. Pixels with the first
+	/// color belong to region 1, pixels with the second color to region 2, and so on; pixels of any other
+	/// color belong to region 0. It's resized to match the (scaled) target, and the bounding box of each
+	/// of the colors' regions is returned, in target coordinates.
+	pub fn set_label_map(&mut self, label_map: DynamicImage, colors: &[(u8, u8, u8)]) -> Vec<Rectangle<u32>> {
+		let (width, height) = self.target.dimensions();
+		let mut map = label_map.to_rgb8();
+		if map.dimensions() != (width, height) {
+			// Colors can't be interpolated, or region borders would get new colors
+			map = imageops_resize(&map, width, height, FilterType::Nearest);
+		}
+		self.labels = get_labels(&map, colors);
+		get_label_bounds(&self.labels, width, colors.len() + 1).split_off(1)
+	}
+
+	pub fn set_region_weight(&mut self, region_weight: RegionWeight) {
+		self.region_weight = region_weight;
+	}
+
+	/// Sets where painters place new elements. With error placement, an error map of the current
+	/// difference of each pixel is kept and passed to painters.
+	pub fn set_placement(&mut self, placement: Placement) {
+		self.placement = placement;
+	}
+
+	/// Sets how many times each accepted element is randomly changed (moved, resized, recolored, or made
+	/// more or less opaque) before being committed, keeping any changes that bring it closer to the target.
+	pub fn set_refine_steps(&mut self, refine_steps: u32) {
+		self.refine_steps = refine_steps;
+	}
+
+	/// Sets how the colors of new elements are decided. With optimal colors, elements painted with normal
+	/// blending have their fill replaced with the flat color that brings their pixels closest to the target;
+	/// other modes keep the colors picked by painters.
+	pub fn set_color_mode(&mut self, color_mode: ColorMode) {
+		self.color_mode = color_mode;
+	}
+
+	/// Sets how candidates are accepted. With simulated annealing, candidates that are worse than the
+	/// current image can also be accepted, based on a temperature that starts at `temperature` and is
+	/// multiplied by `cooling_rate` after each try.
+	pub fn set_acceptance(&mut self, acceptance: Acceptance, temperature: f64, cooling_rate: f64) {
+		self.acceptor = Acceptor::new(acceptance, temperature, cooling_rate);
+	}
+
+	/// Sets where the next generation process starts from, instead of the first try. The current image
+	/// needs to be the one the state was taken with.
+	pub fn resume(&mut self, state: ProcessState) {
+		self.resume_state = Some(state);
+	}
+
+	/// Runs the generation process. For each candidate, a region is randomly picked based on its area or
+	/// its remaining difference, and then one of the region's painters is randomly picked based on its
+	/// weight; since painters use `rng_seed` for their own random numbers, picking them uses a different
+	/// sequence. Regions are indexed by label, with region 0 being the whole image when there's no label
+	/// map.
+	///
+	/// The candidates of each try are spread over `threads` threads. Their random numbers only depend on
+	/// `rng_seed`, the try, and their index, and ties are broken by index, so the same `candidates` always
+	/// produce the same image regardless of the number of threads.
+	#[allow(clippy::too_many_arguments)]
 	pub fn process(
 		&mut self,
 		target_tries: u32,
@@ -103,10 +265,33 @@ impl Generator {
 		target_diff: f64,
 		should_benchmark: bool,
 		candidates: usize,
-		painter: impl Painter + Send + Sync + 'static,
+		threads: usize,
+		regions: Vec<Painters>,
+		rng_seed: u32,
 		cb: Option<ProcessCallback>,
 	) {
-		let mut curr_diff = image_diff(&self.current, &self.target);
+		let resume_state = self.resume_state.take();
+		self.painter_runs.clear();
+
+		// Differences are only computed over the area of each new patch
+		let mut diff_map =
+			DiffMap::new(&self.current, &self.target, self.diff_metric, self.importance_map.as_ref());
+		let region_areas = if self.labels.is_empty() {
+			vec![self.target.len() as u32 / 3; regions.len()]
+		} else {
+			diff_map.set_labels(&self.labels, regions.len());
+			get_label_areas(&self.labels, regions.len())
+		};
+		if let Some(state) = resume_state.as_ref() {
+			diff_map.set_sums(state.diff_total, &state.label_errors);
+		}
+		let (width, height) = self.target.dimensions();
+		let mut arc_error_map = Arc::new(match self.placement {
+			Placement::Uniform => None,
+			Placement::Error => Some(ErrorMap::new(width, height, diff_map.errors())),
+		});
+		let mut arc_diff_map = Arc::new(diff_map);
+		let mut curr_diff = arc_diff_map.diff();
 
 		println!("Starting tries; initial difference from target is {:.2}%.", curr_diff * 100.0);
 
@@ -126,8 +311,71 @@ impl Generator {
 
 		let mut total_processes: u32 = 0;
 
-		let arc_painter = Arc::new(painter);
+		let mut painter_generations: Vec<Vec<u32>> =
+			regions.iter().map(|painters| vec![0u32; painters.len()]).collect();
+		let mut last_region_index: usize = 0;
+		let mut last_painter_index: usize = 0;
+
+		let mut painter_rng = Rng::from_seed(rng_seed);
+		let painter_rng_seed = painter_rng.next();
+		let region_rng_seed = painter_rng.next();
+		let refine_rng_seed = painter_rng.next();
+		let acceptance_rng_seed = painter_rng.next();
+		let mut acceptor = self.acceptor.clone();
+
+		let mut time_elapsed_before: f64 = 0.0;
+		if let Some(state) = resume_state {
+			curr_tries = state.num_tries;
+			curr_generations = state.num_generations;
+			total_processes = state.num_processes;
+			if state.painter_generations.len() == painter_generations.len() {
+				painter_generations = state.painter_generations;
+				self.painter_runs = state.painter_runs;
+			}
+			time_elapsed_before = state.time_elapsed;
+			acceptor.set_temperature(state.temperature);
+		}
+		let pick_painter = |iteration: u32, painters: &Painters| {
+			if painters.len() == 1 {
+				0
+			} else {
+				get_random_index_weighted(&mut get_pick_rng(painter_rng_seed, iteration), painters)
+			}
+		};
+		let pick_region = |iteration: u32, region_weights: &Vec<WeightedValue<usize>>| {
+			if region_weights.len() == 1 {
+				0
+			} else {
+				get_random_index_weighted(&mut get_pick_rng(region_rng_seed, iteration), region_weights)
+			}
+		};
+
+		let metadata: Vec<Vec<HashMap<String, String>>> = regions
+			.iter()
+			.map(|painters| painters.iter().map(|painter| painter.value.get_metadata()).collect())
+			.collect();
+		let arc_regions = Arc::new(regions);
 		let arc_target = Arc::new(self.target.clone());
+		let arc_paint_mask = Arc::new(self.paint_mask.clone());
+		let arc_importance_map = Arc::new(self.importance_map.clone());
+		let arc_coverage_canvas = Arc::new(match self.color_mode {
+			ColorMode::Optimal => Some(RgbImage::new(width, height)),
+			_ => None,
+		});
+
+		// Threads are only created once, and reused for all tries
+		let workers = if should_benchmark || candidates == 1 {
+			None
+		} else {
+			Some(CandidateWorkers::new(
+				threads.clamp(1, candidates),
+				&arc_regions,
+				&arc_target,
+				&arc_paint_mask,
+				&arc_importance_map,
+				&arc_coverage_canvas,
+			))
+		};
 
 		let mut time_elapsed_try_avg = AverageNumber::new(100);
 		let mut time_elapsed_generation_avg = AverageNumber::new(50);
@@ -145,76 +393,140 @@ impl Generator {
 			benchmarks.whole_try.start();
 			used = false;
 
+			// Regions without pixels are never picked; if no region has any error left, they're picked by area
+			let region_errors: Vec<f64> = match self.region_weight {
+				RegionWeight::Area => vec![],
+				RegionWeight::Error => (0..region_areas.len())
+					.map(|index| arc_diff_map.get_label_error(index).max(0.0))
+					.collect(),
+			};
+			let by_error = region_errors.iter().any(|&error| error > 0.0);
+			let region_weights: Vec<WeightedValue<usize>> = region_areas
+				.iter()
+				.enumerate()
+				.map(|(index, &area)| WeightedValue {
+					value: index,
+					weight: if by_error {
+						region_errors[index]
+					} else {
+						area as f64
+					},
+				})
+				.collect();
+
 			if should_benchmark || candidates == 1 {
 				// Simple path with no concurrency
 				benchmarks.paint.start();
-				let new_candidate =
-					arc_painter.paint(&self.current, total_processes, &self.target).expect("painting");
+				let region_index = pick_region(total_processes, &region_weights);
+				let painter_index = pick_painter(total_processes, &arc_regions[region_index]);
+				let painter = &arc_regions[region_index][painter_index].value;
+				let mut new_shape = painter
+					.get_shape(&self.current, total_processes, &self.target, arc_error_map.as_ref().as_ref())
+					.expect("painting");
+				if let Some(coverage_canvas) = arc_coverage_canvas.as_ref() {
+					set_optimal_color(
+						painter.as_ref(),
+						&mut new_shape,
+						coverage_canvas,
+						&self.current,
+						&self.target,
+						arc_paint_mask.as_ref().as_ref(),
+						arc_importance_map.as_ref().as_ref(),
+					);
+				}
+				let mut new_candidate = painter.paint_shape(&self.current, &new_shape).expect("painting");
+				if let Some(paint_mask) = arc_paint_mask.as_ref() {
+					new_candidate.mask(&self.current, paint_mask);
+				}
 				benchmarks.paint.stop();
 
 				benchmarks.diff.start();
-				let new_diff = image_diff(&new_candidate, &self.target);
+				let mut new_diff = arc_diff_map.get_patch_diff(&new_candidate, &self.target);
 				benchmarks.diff.stop();
 
-				if new_diff < curr_diff {
-					self.current = new_candidate;
+				if acceptor.accepts(curr_diff, new_diff, &mut get_rng(acceptance_rng_seed, total_processes)) {
+					if self.refine_steps > 0 {
+						(new_candidate, new_diff) = refine_shape(
+							painter.as_ref(),
+							&self.current,
+							&self.target,
+							&arc_diff_map,
+							arc_paint_mask.as_ref().as_ref(),
+							(new_shape, new_candidate, new_diff),
+							self.refine_steps,
+							&mut get_rng(refine_rng_seed, total_processes),
+						);
+					}
+					new_candidate.apply(&mut self.current);
+					let diff_map = Arc::get_mut(&mut arc_diff_map).expect("updating the diff map");
+					let area = diff_map.apply(&new_candidate, &self.target);
+					if let Some(error_map) = Arc::get_mut(&mut arc_error_map).expect("updating the error map")
+					{
+						error_map.update(diff_map.errors(), area.y, area.y + area.height);
+					}
 					curr_diff = new_diff;
+					last_region_index = region_index;
+					last_painter_index = painter_index;
 					used = true;
 				}
 
 				total_processes = total_processes.wrapping_add(1);
 			} else {
 				// Complex path with concurrency
-				// This can sometimes be slower because of all the image cloning done,
-				// and will use more memory, but on the aggregate reaches successfull
-				// generations in about 25% of the original time
-				let (tx, rx) = mpsc::channel();
-
-				for candidate in 0..candidates {
-					let tx1 = mpsc::Sender::clone(&tx);
-					let thread_painter = Arc::clone(&arc_painter);
-					let thread_current = self.current.clone();
-					let thread_target = Arc::clone(&arc_target);
-
-					thread::spawn(move || {
-						let result = match thread_painter.paint(
-							&thread_current,
-							total_processes.wrapping_add(candidate as u32),
-							&thread_target,
-						) {
-							Ok(new_candidate) => {
-								let new_diff = image_diff(&new_candidate, &thread_target);
-
-								// Only report candidates that are actually better than the current diff,
-								// to minimize the back-and-forth of data. To be fair, however, this doesn't
-								// seem to to do much in terms of performance.
-								if new_diff < curr_diff {
-									ProcessResult::Ok(new_candidate, new_diff)
-								} else {
-									ProcessResult::Ignore
-								}
-							}
-							Err(err) => ProcessResult::Error(err.to_owned()),
-						};
-						tx1.send(result).unwrap();
-					});
-				}
+				// The current image is shared by all candidates while they paint, and only the best
+				// patch is committed back to it once they're all done
+				let workers = workers.as_ref().expect("candidate threads");
+				let arc_current = Arc::new(std::mem::replace(&mut self.current, RgbImage::new(0, 0)));
+				let try_candidates: Vec<Candidate> = (0..candidates)
+					.map(|candidate| {
+						let iteration = total_processes.wrapping_add(candidate as u32);
+						let region_index = pick_region(iteration, &region_weights);
+						let painter_index = pick_painter(iteration, &arc_regions[region_index]);
+						(candidate, iteration, region_index, painter_index)
+					})
+					.collect();
+				let best_candidate = workers.paint(
+					&arc_current,
+					&arc_diff_map,
+					&arc_error_map,
+					curr_diff,
+					&acceptor,
+					try_candidates,
+				);
 
-				drop(tx);
-
-				for result in rx {
-					match result {
-						ProcessResult::Ok(new_candidate, new_diff) => {
-							if new_diff < curr_diff {
-								self.current = new_candidate;
-								curr_diff = new_diff;
-								used = true;
-							}
-						}
-						ProcessResult::Ignore => {}
-						ProcessResult::Error(err) => {
-							panic!("{}", err);
-						}
+				self.current = Arc::try_unwrap(arc_current).expect("candidate threads to release the image");
+				if let Some((new_shape, mut new_candidate, new_diff, _, region_index, painter_index)) =
+					best_candidate.filter(|best| {
+						acceptor.accepts(
+							curr_diff,
+							best.2,
+							&mut get_rng(acceptance_rng_seed, total_processes),
+						)
+					}) {
+					curr_diff = new_diff;
+					last_region_index = region_index;
+					last_painter_index = painter_index;
+					used = true;
+					if self.refine_steps > 0 {
+						(new_candidate, curr_diff) = refine_shape(
+							arc_regions[last_region_index][last_painter_index].value.as_ref(),
+							&self.current,
+							&self.target,
+							&arc_diff_map,
+							arc_paint_mask.as_ref().as_ref(),
+							(*new_shape, new_candidate, curr_diff),
+							self.refine_steps,
+							&mut get_rng(refine_rng_seed, total_processes),
+						);
+					}
+					new_candidate.apply(&mut self.current);
+					let diff_map =
+						Arc::get_mut(&mut arc_diff_map).expect("candidate threads to release the diff map");
+					let area = diff_map.apply(&new_candidate, &self.target);
+					if let Some(error_map) =
+						Arc::get_mut(&mut arc_error_map).expect("candidate threads to release the error map")
+					{
+						error_map.update(diff_map.errors(), area.y, area.y + area.height);
 					}
 				}
 
@@ -223,6 +535,17 @@ impl Generator {
 
 			if used {
 				curr_generations += 1;
+				painter_generations[last_region_index][last_painter_index] += 1;
+				match self.painter_runs.last_mut() {
+					Some(run) if run.region == last_region_index && run.painter == last_painter_index => {
+						run.generations += 1;
+					}
+					_ => self.painter_runs.push(PainterRun {
+						region: last_region_index,
+						painter: last_painter_index,
+						generations: 1,
+					}),
+				}
 
 				// Update time stats for generation
 				benchmarks.generation.stop();
@@ -231,12 +554,16 @@ impl Generator {
 				// Update time stats for diff
 				let diff_change = diff_last_generation - curr_diff;
 				let diff_time = benchmarks.generation.last_ms();
-				let diff_time_per_pct = diff_time / diff_change;
-				time_elapsed_diff_pct_avg.put(diff_time_per_pct);
+				if diff_change > 0.0 {
+					// Worse candidates accepted by simulated annealing don't help estimating the time left
+					let diff_time_per_pct = diff_time / diff_change;
+					time_elapsed_diff_pct_avg.put(diff_time_per_pct);
+				}
 				diff_last_generation = curr_diff;
 			}
 
 			curr_tries += 1;
+			acceptor.cool();
 
 			let finished = (target_tries > 0 && curr_tries == target_tries)
 				|| (target_generations > 0 && curr_generations == target_generations)
@@ -248,6 +575,8 @@ impl Generator {
 
 			if let Some(process_callback) = cb {
 				benchmarks.result_callback.start();
+				let time_elapsed = time_elapsed_before + benchmarks.total.current_ms() / 1000.0;
+				let (diff_total, label_errors) = arc_diff_map.get_sums();
 				process_callback(
 					&self,
 					ProcessCallbackResult {
@@ -256,8 +585,23 @@ impl Generator {
 						num_tries: curr_tries,
 						num_generations: curr_generations,
 						diff: curr_diff,
-						time_elapsed: benchmarks.total.current_ms() as f32 / 1000.0,
-						metadata: arc_painter.get_metadata(),
+						time_elapsed: time_elapsed as f32,
+						metadata: metadata.clone(),
+						painter_generations: painter_generations.clone(),
+						painter_elements: vec![],
+						temperature: acceptor.temperature(),
+						state: Some(ProcessState {
+							rng_seed,
+							num_tries: curr_tries,
+							num_generations: curr_generations,
+							num_processes: total_processes,
+							time_elapsed,
+							temperature: acceptor.temperature(),
+							painter_generations: painter_generations.clone(),
+							diff_total,
+							label_errors: label_errors.to_vec(),
+							painter_runs: vec![],
+						}),
 					},
 				);
 				benchmarks.result_callback.stop();
@@ -301,11 +645,18 @@ impl Generator {
 					print!("{} generations so far, ", curr_generations);
 				}
 
+				// Temperature block
+				if acceptor.acceptance() == Acceptance::Annealing {
+					print!("temperature {:.3e}, ", acceptor.temperature());
+				}
+
 				// Diff block
 				if target_diff > 0.0 {
 					let remaining = curr_diff - target_diff;
-					let time_left =
-						format_time((remaining as f64 * time_elapsed_diff_pct_avg.get().unwrap()).max(0.0));
+					let time_left = match time_elapsed_diff_pct_avg.get() {
+						Ok(time_per_pct) => format_time((remaining as f64 * time_per_pct).max(0.0)),
+						Err(_) => "∞".to_string(),
+					};
 					println!(
 						"new difference is {:.2}%/{:.2}% ({} left)",
 						curr_diff * 100.0,
@@ -327,13 +678,19 @@ impl Generator {
 
 		benchmarks.total.stop();
 
-		let final_diff = image_diff(&self.current, &self.target);
+		let final_diff =
+			DiffMap::new(&self.current, &self.target, self.diff_metric, self.importance_map.as_ref()).diff();
 		println!(
-			"Finished {} tries in {:.3}s ({:.3}ms avg per try), using {} candidate threads.",
+			"Finished {} tries in {:.3}s ({:.3}ms avg per try), using {} candidates over {} threads.",
 			curr_tries,
 			benchmarks.total.last_ms() / 1000.0,
 			benchmarks.whole_try.average_ms(),
-			candidates
+			candidates,
+			if candidates == 1 {
+				1
+			} else {
+				threads.clamp(1, candidates)
+			}
 		);
 		if candidates == 1 {
 			println!(
@@ -357,7 +714,133 @@ impl Generator {
 		println!("The final difference from target is {:.2}%.", final_diff * 100.0);
 	}
 
+	/// Converts an image to a grayscale map with the same dimensions as the target.
+	fn get_target_sized_map(&self, image: DynamicImage) -> GrayImage {
+		let (width, height) = self.target.dimensions();
+		let map = image.to_luma8();
+		if map.dimensions() == (width, height) {
+			map
+		} else {
+			image_resize(&map, width, height)
+		}
+	}
+
+	/// The painter that won each generation of the last generation process, as runs of consecutive
+	/// generations won by the same painter.
+	pub fn get_painter_runs(&self) -> &[PainterRun] {
+		&self.painter_runs
+	}
+
 	pub fn get_current(&self) -> RgbImage {
 		self.current.clone()
 	}
 }
+
+/// Replaces the fill of an element painted with normal blending with its optimal flat color. The coverage
+/// of the element is found by painting it in opaque white over a black canvas.
+fn set_optimal_color(
+	painter: &(dyn Painter + Send + Sync),
+	shape: &mut Shape,
+	coverage_canvas: &RgbImage,
+	current: &RgbImage,
+	target: &RgbImage,
+	paint_mask: Option<&GrayImage>,
+	importance_map: Option<&GrayImage>,
+) {
+	if shape.blending_mode != BlendingMode::Normal {
+		return;
+	}
+	let mut coverage_shape = shape.clone();
+	coverage_shape.alpha = 1.0;
+	coverage_shape.fill = Fill::Flat([255, 255, 255]);
+	if let Ok(coverage) = painter.paint_shape(coverage_canvas, &coverage_shape) {
+		if let Some(color) =
+			get_optimal_color(&coverage, current, target, shape.alpha, paint_mask, importance_map)
+		{
+			shape.fill = Fill::Flat(color);
+		}
+	}
+}
+
+/// Refines an accepted element by randomly changing its shape a number of times, and keeping any changes
+/// that bring it closer to the target. Each change replaces the element instead of painting over it.
+#[allow(clippy::too_many_arguments)]
+fn refine_shape(
+	painter: &(dyn Painter + Send + Sync),
+	current: &RgbImage,
+	target: &RgbImage,
+	diff_map: &DiffMap,
+	paint_mask: Option<&GrayImage>,
+	accepted: (Shape, Patch, f64),
+	steps: u32,
+	rng: &mut Rng,
+) -> (Patch, f64) {
+	let (mut best_shape, mut best_candidate, mut best_diff) = accepted;
+	for _ in 0..steps {
+		let mut new_shape = best_shape.clone();
+		new_shape.mutate(rng);
+		let mut new_candidate = painter.paint_shape(current, &new_shape).expect("painting");
+		if let Some(paint_mask) = paint_mask {
+			new_candidate.mask(current, paint_mask);
+		}
+		let new_diff = diff_map.get_patch_diff(&new_candidate, target);
+		if new_diff < best_diff {
+			best_shape = new_shape;
+			best_candidate = new_candidate;
+			best_diff = new_diff;
+		}
+	}
+	(best_candidate, best_diff)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::cell::Cell;
+
+	use super::*;
+	use crate::generator::painter::circle::CirclePainter;
+	use crate::generator::painter::rect::RectPainter;
+
+	thread_local! {
+		static FINAL_DIFF: Cell<f64> = const { Cell::new(0.0) };
+	}
+
+	fn on_processed(_generator: &Generator, result: ProcessCallbackResult) {
+		if result.is_final {
+			FINAL_DIFF.with(|diff| diff.set(result.diff));
+		}
+	}
+
+	/// Runs a short generation with a fixed seed, returning the final image and its difference.
+	fn generate(threads: usize) -> (RgbImage, f64) {
+		let target =
+			RgbImage::from_fn(24, 24, |x, y| Rgb([(x * 10) as u8, (y * 10) as u8, ((x + y) * 5) as u8]));
+		let mut generator = Generator::from_image(DynamicImage::ImageRgb8(target), 1.0);
+		generator.set_placement(Placement::Error);
+		let rects: Box<dyn Painter + Send + Sync> = Box::new(RectPainter::new());
+		let circles: Box<dyn Painter + Send + Sync> = Box::new(CirclePainter::new());
+		let painters = vec![
+			WeightedValue {
+				value: rects,
+				weight: 1.0,
+			},
+			WeightedValue {
+				value: circles,
+				weight: 2.0,
+			},
+		];
+		generator.process(30, 0, 0.0, false, 8, threads, vec![painters], 7, Some(on_processed));
+		(generator.get_current(), FINAL_DIFF.with(|diff| diff.get()))
+	}
+
+	#[test]
+	fn test_process_independent_of_threads() {
+		let (image_1, diff_1) = generate(1);
+		for threads in [2, 3, 8] {
+			let (image_n, diff_n) = generate(threads);
+			assert_eq!(image_n, image_1);
+			assert_eq!(diff_n, diff_1);
+		}
+		assert!(diff_1 > 0.0);
+	}
+}
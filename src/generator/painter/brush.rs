@@ -0,0 +1,284 @@
+use std::collections::HashMap;
+use std::f64::consts::PI;
+
+use image::{Pixel, Rgb, RgbImage};
+
+use crate::generator::painter::shape::{Shape, ShapeDetails};
+use crate::generator::painter::{
+	get_random_shape, get_rng_seed_metadata, Painter, ShapeGeometry, ShapeOptions,
+};
+use crate::generator::utils::color::BlendingMode;
+use crate::generator::utils::fill::FillType;
+use crate::generator::utils::geom::{cubic_bezier_point, get_edge_alpha, project_to_segment};
+use crate::generator::utils::image::get_luma_gradient;
+use crate::generator::utils::patch::Patch;
+use crate::generator::utils::pixel::blend;
+use crate::generator::utils::placement::ErrorMap;
+use crate::generator::utils::random::{
+	get_random_position, get_random_range, get_random_ranges_bias_weighted,
+	get_random_size_ranges_bias_weighted,
+};
+use crate::generator::utils::units::{Margins, Rectangle, SizeUnit, WeightedValue};
+
+// Minimum luma gradient magnitude for an edge to be followed; below this, the direction is random
+const MIN_EDGE_GRADIENT: f64 = 0.01;
+
+pub struct BrushPainter {
+	pub options: Options,
+}
+
+pub struct Options {
+	pub blending_mode: Vec<WeightedValue<BlendingMode>>,
+	pub fill: Vec<WeightedValue<FillType>>,
+	pub alpha: Vec<WeightedValue<(f64, f64)>>,
+	pub alpha_bias: f64,
+	pub thickness: Vec<WeightedValue<(SizeUnit, SizeUnit)>>,
+	pub thickness_bias: f64, // 0 = normal; -1 = quad bias towards small, 1 = quad bias towards big, etc
+	pub length: Vec<WeightedValue<(SizeUnit, SizeUnit)>>,
+	pub length_bias: f64, // 0 = normal; -1 = quad bias towards small, 1 = quad bias towards big, etc
+	pub taper: Vec<WeightedValue<(f64, f64)>>, // 0 = same thickness everywhere; 1 = pointy ends
+	pub taper_bias: f64,
+	pub curvature: Vec<WeightedValue<(f64, f64)>>, // 0 = straight; 1 = control points as far as the length
+	pub curvature_bias: f64,
+	pub follow_edges: bool,
+	pub anti_alias: bool,
+	pub color_seed: f64,
+	pub rng_seed: u32,
+	pub margins: Margins<SizeUnit>,
+}
+
+impl BrushPainter {
+	pub fn new() -> Self {
+		let options = Options {
+			blending_mode: vec![WeightedValue {
+				value: BlendingMode::default(),
+				weight: 1.0,
+			}],
+			fill: vec![WeightedValue {
+				value: FillType::default(),
+				weight: 1.0,
+			}],
+			alpha: vec![WeightedValue {
+				value: (1.0, 1.0),
+				weight: 1.0,
+			}],
+			alpha_bias: 0.0,
+			thickness: vec![WeightedValue {
+				value: (SizeUnit::Fraction(0.01), SizeUnit::Fraction(0.05)),
+				weight: 1.0,
+			}],
+			thickness_bias: 0.0,
+			length: vec![WeightedValue {
+				value: (SizeUnit::Fraction(0.05), SizeUnit::Fraction(0.3)),
+				weight: 1.0,
+			}],
+			length_bias: 0.0,
+			taper: vec![WeightedValue {
+				value: (0.5, 0.5),
+				weight: 1.0,
+			}],
+			taper_bias: 0.0,
+			curvature: vec![WeightedValue {
+				value: (0.0, 0.5),
+				weight: 1.0,
+			}],
+			curvature_bias: 0.0,
+			follow_edges: false,
+			anti_alias: true,
+			color_seed: 0.0,
+			rng_seed: 0,
+			margins: Margins::<SizeUnit> {
+				top: SizeUnit::Pixels(0),
+				right: SizeUnit::Pixels(0),
+				bottom: SizeUnit::Pixels(0),
+				left: SizeUnit::Pixels(0),
+			},
+		};
+
+		BrushPainter {
+			options,
+		}
+	}
+}
+
+impl Painter for BrushPainter {
+	fn get_shape(
+		&self,
+		canvas: &RgbImage,
+		iteration: u32,
+		seed_map: &RgbImage,
+		error_map: Option<&ErrorMap>,
+	) -> Result<Shape, &str> {
+		let options = ShapeOptions {
+			blending_mode: &self.options.blending_mode,
+			fill: &self.options.fill,
+			alpha: &self.options.alpha,
+			alpha_bias: self.options.alpha_bias,
+			color_seed: self.options.color_seed,
+			rng_seed: self.options.rng_seed,
+			margins: &self.options.margins,
+		};
+		get_random_shape(&options, canvas, iteration, seed_map, |rng, target_area, target_visible_area| {
+			// Find random dimensions for the stroke to be painted
+			let max_dimension = target_visible_area.0.min(target_visible_area.1);
+			let thickness = get_random_size_ranges_bias_weighted(
+				rng,
+				&self.options.thickness,
+				self.options.thickness_bias,
+				max_dimension,
+			);
+			let length = get_random_size_ranges_bias_weighted(
+				rng,
+				&self.options.length,
+				self.options.length_bias,
+				max_dimension,
+			);
+			let taper = get_random_ranges_bias_weighted(rng, &self.options.taper, self.options.taper_bias)
+				.clamp(0.0, 1.0);
+			let curvature =
+				get_random_ranges_bias_weighted(rng, &self.options.curvature, self.options.curvature_bias);
+
+			// Find a center, and the direction of the stroke
+			let (mut center_x, mut center_y) = get_random_position(
+				rng,
+				error_map,
+				(target_area.x as f64, (target_area.x + target_area.width) as f64),
+				(target_area.y as f64, (target_area.y + target_area.height) as f64),
+			);
+			let random_angle = get_random_range(rng, 0.0, PI * 2.0);
+			let angle = if self.options.follow_edges {
+				let seed_x = center_x.max(0.0).min(seed_map.width() as f64 - 1.0) as u32;
+				let seed_y = center_y.max(0.0).min(seed_map.height() as f64 - 1.0) as u32;
+				let (gradient_x, gradient_y) = get_luma_gradient(seed_map, seed_x, seed_y);
+				if gradient_x.hypot(gradient_y) >= MIN_EDGE_GRADIENT {
+					// Edges run perpendicular to the gradient
+					gradient_y.atan2(gradient_x) + PI / 2.0
+				} else {
+					random_angle
+				}
+			} else {
+				random_angle
+			};
+
+			// Create the control points, relative to the center: the ends are along the direction, and the
+			// middle points are offset perpendicularly by the curvature
+			let (sin, cos) = angle.sin_cos();
+			let bend_1 = get_random_range(rng, -1.0, 1.0) * curvature * length;
+			let bend_2 = get_random_range(rng, -1.0, 1.0) * curvature * length;
+			let points =
+				[(-length / 2.0, 0.0), (-length / 6.0, bend_1), (length / 6.0, bend_2), (length / 2.0, 0.0)]
+					.map(|(along, across)| (along * cos - across * sin, along * sin + across * cos));
+
+			// Move the stroke so it stays inside the target area, when possible
+			let (hull_x1, hull_y1, hull_x2, hull_y2) = get_brush_hull(&points, thickness / 2.0);
+			center_x = center_x
+				.min((target_area.x + target_area.width) as f64 - hull_x2)
+				.max(target_area.x as f64 - hull_x1);
+			center_y = center_y
+				.min((target_area.y + target_area.height) as f64 - hull_y2)
+				.max(target_area.y as f64 - hull_y1);
+
+			ShapeGeometry {
+				x: center_x,
+				y: center_y,
+				seed_point: (center_x, center_y),
+				bounds: Rectangle {
+					x: center_x + hull_x1,
+					y: center_y + hull_y1,
+					width: hull_x2 - hull_x1,
+					height: hull_y2 - hull_y1,
+				},
+				details: ShapeDetails::Brushes {
+					points,
+					thickness,
+					taper,
+					length,
+				},
+			}
+		})
+	}
+
+	fn paint_shape(&self, canvas: &RgbImage, shape: &Shape) -> Result<Patch, &str> {
+		let (points, half_thickness, taper, length) = match shape.details {
+			ShapeDetails::Brushes {
+				points,
+				thickness,
+				taper,
+				length,
+			} => (
+				points.map(|(x, y)| (x * shape.scale, y * shape.scale)),
+				thickness * shape.scale / 2.0,
+				taper,
+				length * shape.scale,
+			),
+			_ => return Err("Cannot paint shape from another painter"),
+		};
+		let (center_x, center_y) = (shape.x, shape.y);
+		let image_area = canvas.dimensions();
+		let (hull_x1, hull_y1, hull_x2, hull_y2) = get_brush_hull(&points, half_thickness);
+		let points = points.map(|(x, y)| (x + center_x, y + center_y));
+
+		// Flatten the curve into segments
+		let num_segments = (length / 4.0).ceil().clamp(4.0, 64.0) as usize;
+		let path: Vec<(f64, f64)> = (0..=num_segments)
+			.map(|i| {
+				cubic_bezier_point(points[0], points[1], points[2], points[3], i as f64 / num_segments as f64)
+			})
+			.collect();
+
+		// Find final, round positions
+		let x1 = (center_x + hull_x1).floor().max(0.0).min(image_area.0 as f64) as u32;
+		let y1 = (center_y + hull_y1).floor().max(0.0).min(image_area.1 as f64) as u32;
+		let x2 = (center_x + hull_x2).ceil().max(0.0).min(image_area.0 as f64) as u32;
+		let y2 = (center_y + hull_y2).ceil().max(0.0).min(image_area.1 as f64) as u32;
+
+		// Finally, paint
+		let mut patch = Patch::from_canvas(canvas, x1, y1, x2, y2);
+		for x in x1..x2 {
+			for y in y1..y2 {
+				// Find how far inside the stroke this pixel is, taking the tapering into account
+				let mut dist = f64::MIN;
+				for i in 0..num_segments {
+					let (segment_dist, segment_t) = project_to_segment(
+						x as f64,
+						y as f64,
+						path[i].0,
+						path[i].1,
+						path[i + 1].0,
+						path[i + 1].1,
+					);
+					let t = (i as f64 + segment_t) / num_segments as f64;
+					let stroke_thickness = half_thickness * (1.0 - taper + taper * (t * PI).sin());
+					dist = dist.max(stroke_thickness - segment_dist);
+				}
+				if dist > 0.0 {
+					let new_alpha = get_edge_alpha(dist, self.options.anti_alias);
+					let new_pixel = Rgb(blend(
+						patch.get_pixel(x, y).channels(),
+						&shape.get_color(x as f64, y as f64),
+						new_alpha * shape.alpha,
+						&shape.blending_mode,
+					));
+					patch.put_pixel(x, y, new_pixel);
+				}
+			}
+		}
+
+		Ok(patch)
+	}
+
+	fn get_metadata(&self) -> HashMap<String, String> {
+		get_rng_seed_metadata(self.options.rng_seed)
+	}
+}
+
+/// Finds the bounds of a stroke relative to its center; since a Bezier curve is always inside the hull
+/// of its control points, we can use them as the bounds
+fn get_brush_hull(points: &[(f64, f64); 4], half_thickness: f64) -> (f64, f64, f64, f64) {
+	(
+		points.iter().map(|p| p.0).fold(f64::MAX, f64::min) - half_thickness,
+		points.iter().map(|p| p.1).fold(f64::MAX, f64::min) - half_thickness,
+		points.iter().map(|p| p.0).fold(f64::MIN, f64::max) + half_thickness,
+		points.iter().map(|p| p.1).fold(f64::MIN, f64::max) + half_thickness,
+	)
+}
@@ -2,15 +2,19 @@ use std::collections::HashMap;
 
 use image::{Pixel, Rgb, RgbImage};
 
+use crate::generator::painter::shape::{Shape, ShapeDetails};
 use crate::generator::painter::Painter;
 use crate::generator::utils::color::BlendingMode;
-use crate::generator::utils::geom::{distance, find_target_draw_rect};
+use crate::generator::utils::fill::{get_random_fill, FillType};
+use crate::generator::utils::geom::{distance, find_target_draw_rect, get_edge_alpha};
+use crate::generator::utils::patch::Patch;
 use crate::generator::utils::pixel::{blend, blend_linear};
+use crate::generator::utils::placement::ErrorMap;
 use crate::generator::utils::random::{
-	get_random_entry_weighted, get_random_range, get_random_ranges_bias_weighted,
+	get_random_entry_weighted, get_random_position, get_random_ranges_bias_weighted,
 	get_random_size_ranges_bias_weighted, get_rng,
 };
-use crate::generator::utils::units::{Margins, SizeUnit, WeightedValue};
+use crate::generator::utils::units::{Margins, Rectangle, SizeUnit, WeightedValue};
 use crate::generator::utils::{image::get_pixel_interpolated, random::get_random_color};
 
 pub struct CirclePainter {
@@ -19,6 +23,7 @@ pub struct CirclePainter {
 
 pub struct Options {
 	pub blending_mode: Vec<WeightedValue<BlendingMode>>,
+	pub fill: Vec<WeightedValue<FillType>>,
 	pub alpha: Vec<WeightedValue<(f64, f64)>>,
 	pub alpha_bias: f64,
 	pub radius: Vec<WeightedValue<(SizeUnit, SizeUnit)>>,
@@ -36,6 +41,10 @@ impl CirclePainter {
 				value: BlendingMode::default(),
 				weight: 1.0,
 			}],
+			fill: vec![WeightedValue {
+				value: FillType::default(),
+				weight: 1.0,
+			}],
 			alpha: vec![WeightedValue {
 				value: (1.0, 1.0),
 				weight: 1.0,
@@ -64,7 +73,13 @@ impl CirclePainter {
 }
 
 impl Painter for CirclePainter {
-	fn paint(&self, canvas: &RgbImage, iteration: u32, seed_map: &RgbImage) -> Result<RgbImage, &str> {
+	fn get_shape(
+		&self,
+		canvas: &RgbImage,
+		iteration: u32,
+		seed_map: &RgbImage,
+		error_map: Option<&ErrorMap>,
+	) -> Result<Shape, &str> {
 		let mut rng = get_rng(self.options.rng_seed, iteration);
 
 		let image_area = canvas.dimensions();
@@ -85,23 +100,13 @@ impl Painter for CirclePainter {
 		);
 
 		// Distribute along the axis too
-		let circle_x = get_random_range(
-			&mut rng,
-			target_area.x as f64 + radius,
-			(target_area.x + target_area.width) as f64 - radius,
-		);
-		let circle_y = get_random_range(
+		let (circle_x, circle_y) = get_random_position(
 			&mut rng,
-			target_area.y as f64 + radius,
-			(target_area.y + target_area.height) as f64 - radius,
+			error_map,
+			(target_area.x as f64 + radius, (target_area.x + target_area.width) as f64 - radius),
+			(target_area.y as f64 + radius, (target_area.y + target_area.height) as f64 - radius),
 		);
 
-		// Find final, round positions
-		let x1 = (circle_x - radius).floor().max(0.0).min(image_area.0 as f64) as u32;
-		let y1 = (circle_y - radius).floor().max(0.0).min(image_area.1 as f64) as u32;
-		let x2 = (circle_x + radius).ceil().max(0.0).min(image_area.0 as f64) as u32;
-		let y2 = (circle_y + radius).ceil().max(0.0).min(image_area.1 as f64) as u32;
-
 		// Determine color
 		let random_color = get_random_color(&mut rng);
 		let seed_color = get_pixel_interpolated(seed_map, circle_x, circle_y);
@@ -111,38 +116,72 @@ impl Painter for CirclePainter {
 		// Decide on blending mode
 		let blending_mode = get_random_entry_weighted(&mut rng, &self.options.blending_mode);
 
+		// Decide on fill
+		let bounds = Rectangle {
+			x: circle_x - radius,
+			y: circle_y - radius,
+			width: radius * 2.0,
+			height: radius * 2.0,
+		};
+		let fill = get_random_fill(
+			&mut rng,
+			&self.options.fill,
+			&color,
+			&random_color,
+			seed_map,
+			self.options.color_seed,
+			&bounds,
+		);
+
+		Ok(Shape::new(
+			circle_x,
+			circle_y,
+			alpha,
+			blending_mode.clone(),
+			fill,
+			target_area,
+			&bounds,
+			ShapeDetails::Circles {
+				radius,
+			},
+		))
+	}
+
+	fn paint_shape(&self, canvas: &RgbImage, shape: &Shape) -> Result<Patch, &str> {
+		let radius = match shape.details {
+			ShapeDetails::Circles {
+				radius,
+			} => radius * shape.scale,
+			_ => return Err("Cannot paint shape from another painter"),
+		};
+		let (circle_x, circle_y) = (shape.x, shape.y);
+		let image_area = canvas.dimensions();
+
+		// Find final, round positions
+		let x1 = (circle_x - radius).floor().max(0.0).min(image_area.0 as f64) as u32;
+		let y1 = (circle_y - radius).floor().max(0.0).min(image_area.1 as f64) as u32;
+		let x2 = (circle_x + radius).ceil().max(0.0).min(image_area.0 as f64) as u32;
+		let y2 = (circle_y + radius).ceil().max(0.0).min(image_area.1 as f64) as u32;
+
 		// Finally, paint
-		let mut painted_canvas = canvas.clone();
+		let mut patch = Patch::from_canvas(canvas, x1, y1, x2, y2);
 		for x in x1..x2 {
 			for y in y1..y2 {
 				let dist = distance(circle_x, circle_y, x as f64, y as f64);
 				if dist <= radius {
-					let abs = radius - dist;
-					let new_alpha = if abs > 1.0 {
-						1.0
-					} else {
-						if self.options.anti_alias {
-							abs
-						} else {
-							if abs >= 0.5 {
-								1.0
-							} else {
-								0.0
-							}
-						}
-					};
+					let new_alpha = get_edge_alpha(radius - dist, self.options.anti_alias);
 					let new_pixel = Rgb(blend(
-						painted_canvas.get_pixel(x, y).channels(),
-						&color,
-						new_alpha * alpha,
-						&blending_mode,
+						patch.get_pixel(x, y).channels(),
+						&shape.get_color(x as f64, y as f64),
+						new_alpha * shape.alpha,
+						&shape.blending_mode,
 					));
-					painted_canvas.put_pixel(x, y, new_pixel);
+					patch.put_pixel(x, y, new_pixel);
 				}
 			}
 		}
 
-		Ok(painted_canvas)
+		Ok(patch)
 	}
 
 	fn get_metadata(&self) -> HashMap<String, String> {
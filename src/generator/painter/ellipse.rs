@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+
+use image::{Pixel, Rgb, RgbImage};
+
+use crate::generator::painter::shape::{Shape, ShapeDetails};
+use crate::generator::painter::{
+	get_random_shape, get_rng_seed_metadata, Painter, ShapeGeometry, ShapeOptions,
+};
+use crate::generator::utils::color::BlendingMode;
+use crate::generator::utils::fill::FillType;
+use crate::generator::utils::geom::{ellipse_extents, ellipse_inner_distance, get_edge_alpha};
+use crate::generator::utils::patch::Patch;
+use crate::generator::utils::pixel::blend;
+use crate::generator::utils::placement::ErrorMap;
+use crate::generator::utils::random::{
+	get_random_position, get_random_ranges_bias_weighted, get_random_size_ranges_bias_weighted,
+};
+use crate::generator::utils::units::{Margins, Rectangle, SizeUnit, WeightedValue};
+
+pub struct EllipsePainter {
+	pub options: Options,
+}
+
+pub struct Options {
+	pub blending_mode: Vec<WeightedValue<BlendingMode>>,
+	pub fill: Vec<WeightedValue<FillType>>,
+	pub alpha: Vec<WeightedValue<(f64, f64)>>,
+	pub alpha_bias: f64,
+	pub radius_x: Vec<WeightedValue<(SizeUnit, SizeUnit)>>,
+	pub radius_x_bias: f64, // 0 = normal; -1 = quad bias towards small, 1 = quad bias towards big, etc
+	pub radius_y: Vec<WeightedValue<(SizeUnit, SizeUnit)>>,
+	pub radius_y_bias: f64, // 0 = normal; -1 = quad bias towards small, 1 = quad bias towards big, etc
+	pub rotation: Vec<WeightedValue<(f64, f64)>>, // In degrees
+	pub rotation_bias: f64,
+	pub anti_alias: bool,
+	pub color_seed: f64,
+	pub rng_seed: u32,
+	pub margins: Margins<SizeUnit>,
+}
+
+impl EllipsePainter {
+	pub fn new() -> Self {
+		let options = Options {
+			blending_mode: vec![WeightedValue {
+				value: BlendingMode::default(),
+				weight: 1.0,
+			}],
+			fill: vec![WeightedValue {
+				value: FillType::default(),
+				weight: 1.0,
+			}],
+			alpha: vec![WeightedValue {
+				value: (1.0, 1.0),
+				weight: 1.0,
+			}],
+			alpha_bias: 0.0,
+			radius_x: vec![WeightedValue {
+				value: (SizeUnit::Fraction(0.0), SizeUnit::Fraction(0.5)),
+				weight: 1.0,
+			}],
+			radius_x_bias: 0.0,
+			radius_y: vec![WeightedValue {
+				value: (SizeUnit::Fraction(0.0), SizeUnit::Fraction(0.5)),
+				weight: 1.0,
+			}],
+			radius_y_bias: 0.0,
+			rotation: vec![WeightedValue {
+				value: (0.0, 0.0),
+				weight: 1.0,
+			}],
+			rotation_bias: 0.0,
+			anti_alias: true,
+			color_seed: 0.0,
+			rng_seed: 0,
+			margins: Margins::<SizeUnit> {
+				top: SizeUnit::Pixels(0),
+				right: SizeUnit::Pixels(0),
+				bottom: SizeUnit::Pixels(0),
+				left: SizeUnit::Pixels(0),
+			},
+		};
+
+		EllipsePainter {
+			options,
+		}
+	}
+}
+
+impl Painter for EllipsePainter {
+	fn get_shape(
+		&self,
+		canvas: &RgbImage,
+		iteration: u32,
+		seed_map: &RgbImage,
+		error_map: Option<&ErrorMap>,
+	) -> Result<Shape, &str> {
+		let options = ShapeOptions {
+			blending_mode: &self.options.blending_mode,
+			fill: &self.options.fill,
+			alpha: &self.options.alpha,
+			alpha_bias: self.options.alpha_bias,
+			color_seed: self.options.color_seed,
+			rng_seed: self.options.rng_seed,
+			margins: &self.options.margins,
+		};
+		get_random_shape(&options, canvas, iteration, seed_map, |rng, target_area, target_visible_area| {
+			// Find random radii and rotation for the ellipse to be painted
+			let radius_x = get_random_size_ranges_bias_weighted(
+				rng,
+				&self.options.radius_x,
+				self.options.radius_x_bias,
+				target_visible_area.0,
+			);
+			let radius_y = get_random_size_ranges_bias_weighted(
+				rng,
+				&self.options.radius_y,
+				self.options.radius_y_bias,
+				target_visible_area.1,
+			);
+			let rotation =
+				get_random_ranges_bias_weighted(rng, &self.options.rotation, self.options.rotation_bias)
+					.to_radians();
+			let (extent_x, extent_y) = ellipse_extents(radius_x, radius_y, rotation);
+
+			// Distribute along the axis too
+			let (ellipse_x, ellipse_y) = get_random_position(
+				rng,
+				error_map,
+				(target_area.x as f64 + extent_x, (target_area.x + target_area.width) as f64 - extent_x),
+				(target_area.y as f64 + extent_y, (target_area.y + target_area.height) as f64 - extent_y),
+			);
+
+			ShapeGeometry {
+				x: ellipse_x,
+				y: ellipse_y,
+				seed_point: (ellipse_x, ellipse_y),
+				bounds: Rectangle {
+					x: ellipse_x - extent_x,
+					y: ellipse_y - extent_y,
+					width: extent_x * 2.0,
+					height: extent_y * 2.0,
+				},
+				details: ShapeDetails::Ellipses {
+					radius_x,
+					radius_y,
+					rotation,
+				},
+			}
+		})
+	}
+
+	fn paint_shape(&self, canvas: &RgbImage, shape: &Shape) -> Result<Patch, &str> {
+		let (radius_x, radius_y, rotation) = match shape.details {
+			ShapeDetails::Ellipses {
+				radius_x,
+				radius_y,
+				rotation,
+			} => (radius_x * shape.scale, radius_y * shape.scale, rotation),
+			_ => return Err("Cannot paint shape from another painter"),
+		};
+		let (ellipse_x, ellipse_y) = (shape.x, shape.y);
+		let image_area = canvas.dimensions();
+		let (extent_x, extent_y) = ellipse_extents(radius_x, radius_y, rotation);
+
+		// Find final, round positions
+		let x1 = (ellipse_x - extent_x).floor().max(0.0).min(image_area.0 as f64) as u32;
+		let y1 = (ellipse_y - extent_y).floor().max(0.0).min(image_area.1 as f64) as u32;
+		let x2 = (ellipse_x + extent_x).ceil().max(0.0).min(image_area.0 as f64) as u32;
+		let y2 = (ellipse_y + extent_y).ceil().max(0.0).min(image_area.1 as f64) as u32;
+
+		// Finally, paint
+		let mut patch = Patch::from_canvas(canvas, x1, y1, x2, y2);
+		for x in x1..x2 {
+			for y in y1..y2 {
+				let dist = ellipse_inner_distance(
+					x as f64, y as f64, ellipse_x, ellipse_y, radius_x, radius_y, rotation,
+				);
+				if dist > 0.0 {
+					let new_alpha = get_edge_alpha(dist, self.options.anti_alias);
+					let new_pixel = Rgb(blend(
+						patch.get_pixel(x, y).channels(),
+						&shape.get_color(x as f64, y as f64),
+						new_alpha * shape.alpha,
+						&shape.blending_mode,
+					));
+					patch.put_pixel(x, y, new_pixel);
+				}
+			}
+		}
+
+		Ok(patch)
+	}
+
+	fn get_metadata(&self) -> HashMap<String, String> {
+		get_rng_seed_metadata(self.options.rng_seed)
+	}
+}
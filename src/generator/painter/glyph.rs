@@ -0,0 +1,259 @@
+use std::collections::HashMap;
+
+use ab_glyph::{Font, FontArc, Glyph, OutlinedGlyph, PxScale};
+use image::{GrayImage, Luma, Pixel, Rgb, RgbImage};
+
+use crate::generator::painter::shape::{Shape, ShapeDetails};
+use crate::generator::painter::{
+	get_random_shape, get_rng_seed_metadata, Painter, ShapeGeometry, ShapeOptions,
+};
+use crate::generator::utils::color::BlendingMode;
+use crate::generator::utils::fill::FillType;
+use crate::generator::utils::geom::{rect_extents, rotate_point};
+use crate::generator::utils::image::get_mask_value_interpolated;
+use crate::generator::utils::patch::Patch;
+use crate::generator::utils::pixel::blend;
+use crate::generator::utils::placement::ErrorMap;
+use crate::generator::utils::random::{
+	get_random_position, get_random_ranges_bias_weighted, get_random_size_ranges_bias_weighted,
+};
+use crate::generator::utils::units::{Margins, Rectangle, SizeUnit, WeightedValue};
+
+pub struct GlyphPainter {
+	pub options: Options,
+}
+
+pub struct Options {
+	pub blending_mode: Vec<WeightedValue<BlendingMode>>,
+	pub fill: Vec<WeightedValue<FillType>>,
+	pub alpha: Vec<WeightedValue<(f64, f64)>>,
+	pub alpha_bias: f64,
+	pub font: Option<FontArc>,
+	pub characters: Vec<char>, // Picked with the same chance; see `get_paintable_characters()`
+	pub font_size: Vec<WeightedValue<(SizeUnit, SizeUnit)>>,
+	pub font_size_bias: f64, // 0 = normal; -1 = quad bias towards small, 1 = quad bias towards big, etc
+	pub rotation: Vec<WeightedValue<(f64, f64)>>, // In degrees
+	pub rotation_bias: f64,
+	pub color_seed: f64,
+	pub rng_seed: u32,
+	pub margins: Margins<SizeUnit>,
+}
+
+impl GlyphPainter {
+	pub fn new() -> Self {
+		let options = Options {
+			blending_mode: vec![WeightedValue {
+				value: BlendingMode::default(),
+				weight: 1.0,
+			}],
+			fill: vec![WeightedValue {
+				value: FillType::default(),
+				weight: 1.0,
+			}],
+			alpha: vec![WeightedValue {
+				value: (1.0, 1.0),
+				weight: 1.0,
+			}],
+			alpha_bias: 0.0,
+			font: None,
+			characters: vec![],
+			font_size: vec![WeightedValue {
+				value: (SizeUnit::Fraction(0.05), SizeUnit::Fraction(0.2)),
+				weight: 1.0,
+			}],
+			font_size_bias: 0.0,
+			rotation: vec![WeightedValue {
+				value: (0.0, 0.0),
+				weight: 1.0,
+			}],
+			rotation_bias: 0.0,
+			color_seed: 0.0,
+			rng_seed: 0,
+			margins: Margins::<SizeUnit> {
+				top: SizeUnit::Pixels(0),
+				right: SizeUnit::Pixels(0),
+				bottom: SizeUnit::Pixels(0),
+				left: SizeUnit::Pixels(0),
+			},
+		};
+
+		GlyphPainter {
+			options,
+		}
+	}
+
+	/// Finds which characters can be painted with a font. When a list of characters is given, it's
+	/// filtered by the ones the font supports (keeping repeated characters, so they're picked more
+	/// often); otherwise, all characters the font supports are used.
+	pub fn get_paintable_characters(font: &FontArc, characters: &str) -> Vec<char> {
+		let candidates: Vec<char> = if characters.is_empty() {
+			let mut font_characters: Vec<char> =
+				font.codepoint_ids().map(|(_, character)| character).collect();
+			font_characters.sort_unstable();
+			font_characters.dedup();
+			font_characters
+		} else {
+			characters.chars().collect()
+		};
+
+		candidates
+			.into_iter()
+			.filter(|character| {
+				!character.is_whitespace() && !character.is_control() && font.glyph_id(*character).0 != 0
+			})
+			.collect()
+	}
+}
+
+impl Painter for GlyphPainter {
+	fn get_shape(
+		&self,
+		canvas: &RgbImage,
+		iteration: u32,
+		seed_map: &RgbImage,
+		error_map: Option<&ErrorMap>,
+	) -> Result<Shape, &str> {
+		let font = match &self.options.font {
+			Some(font) => font,
+			None => return Err("No font to paint with"),
+		};
+		if self.options.characters.is_empty() {
+			return Err("No characters to paint with");
+		}
+
+		let options = ShapeOptions {
+			blending_mode: &self.options.blending_mode,
+			fill: &self.options.fill,
+			alpha: &self.options.alpha,
+			alpha_bias: self.options.alpha_bias,
+			color_seed: self.options.color_seed,
+			rng_seed: self.options.rng_seed,
+			margins: &self.options.margins,
+		};
+		get_random_shape(&options, canvas, iteration, seed_map, |rng, target_area, target_visible_area| {
+			// Pick a character, and find the size of its mask; characters without an outline (like spaces)
+			// are still picked, but paint nothing
+			let character_index = rng.next_u32_range(0, self.options.characters.len() as u32) as usize;
+			let character = self.options.characters[character_index.min(self.options.characters.len() - 1)];
+			let font_size = get_random_size_ranges_bias_weighted(
+				rng,
+				&self.options.font_size,
+				self.options.font_size_bias,
+				target_visible_area.0.min(target_visible_area.1),
+			);
+			let (mask_w, mask_h) = match font.outline_glyph(get_glyph(font, character, font_size)) {
+				Some(outlined_glyph) => get_mask_dimensions(&outlined_glyph),
+				None => (1, 1),
+			};
+
+			// Rotate it
+			let rotation =
+				get_random_ranges_bias_weighted(rng, &self.options.rotation, self.options.rotation_bias)
+					.to_radians();
+			let (extent_x, extent_y) = rect_extents(mask_w as f64, mask_h as f64, rotation);
+
+			// Distribute along the axis too, keeping the whole rotated glyph inside the target area
+			let (center_x, center_y) = get_random_position(
+				rng,
+				error_map,
+				(target_area.x as f64 + extent_x, (target_area.x + target_area.width) as f64 - extent_x),
+				(target_area.y as f64 + extent_y, (target_area.y + target_area.height) as f64 - extent_y),
+			);
+
+			ShapeGeometry {
+				x: center_x,
+				y: center_y,
+				seed_point: (center_x, center_y),
+				bounds: Rectangle {
+					x: center_x - extent_x,
+					y: center_y - extent_y,
+					width: extent_x * 2.0,
+					height: extent_y * 2.0,
+				},
+				details: ShapeDetails::Glyphs {
+					character,
+					font_size,
+					rotation,
+				},
+			}
+		})
+	}
+
+	fn paint_shape(&self, canvas: &RgbImage, shape: &Shape) -> Result<Patch, &str> {
+		let font = match &self.options.font {
+			Some(font) => font,
+			None => return Err("No font to paint with"),
+		};
+		let (character, font_size, rotation) = match shape.details {
+			ShapeDetails::Glyphs {
+				character,
+				font_size,
+				rotation,
+			} => (character, font_size * shape.scale, rotation),
+			_ => return Err("Cannot paint shape from another painter"),
+		};
+		let (center_x, center_y) = (shape.x, shape.y);
+		let image_area = canvas.dimensions();
+
+		// Rasterize the character into a mask
+		let outlined_glyph = match font.outline_glyph(get_glyph(font, character, font_size)) {
+			Some(outlined_glyph) => outlined_glyph,
+			None => return Ok(Patch::empty()), // Nothing to paint
+		};
+		let (mask_w, mask_h) = get_mask_dimensions(&outlined_glyph);
+		let mut mask = GrayImage::new(mask_w, mask_h);
+		outlined_glyph.draw(|x, y, coverage| {
+			if x < mask_w && y < mask_h {
+				mask.put_pixel(x, y, Luma([(coverage.clamp(0.0, 1.0) * 255.0).round() as u8]));
+			}
+		});
+		let (extent_x, extent_y) = rect_extents(mask_w as f64, mask_h as f64, rotation);
+
+		// Find final, round positions
+		let x1 = (center_x - extent_x).floor().max(0.0).min(image_area.0 as f64) as u32;
+		let x2 = (center_x + extent_x + 1.0).ceil().max(0.0).min(image_area.0 as f64) as u32;
+		let y1 = (center_y - extent_y).floor().max(0.0).min(image_area.1 as f64) as u32;
+		let y2 = (center_y + extent_y + 1.0).ceil().max(0.0).min(image_area.1 as f64) as u32;
+
+		// Finally, paint; every pixel of the rotated bounds reads the glyph coverage at its unrotated position
+		let mask_center_x = mask_w as f64 / 2.0 - 0.5;
+		let mask_center_y = mask_h as f64 / 2.0 - 0.5;
+		let mut patch = Patch::from_canvas(canvas, x1, y1, x2, y2);
+		for x in x1..x2 {
+			for y in y1..y2 {
+				let (local_x, local_y) = rotate_point(x as f64, y as f64, center_x, center_y, -rotation);
+				let new_alpha = get_mask_value_interpolated(
+					&mask,
+					local_x - center_x + mask_center_x,
+					local_y - center_y + mask_center_y,
+				);
+				if new_alpha > 0.0 {
+					let new_pixel = Rgb(blend(
+						patch.get_pixel(x, y).channels(),
+						&shape.get_color(x as f64, y as f64),
+						new_alpha * shape.alpha,
+						&shape.blending_mode,
+					));
+					patch.put_pixel(x, y, new_pixel);
+				}
+			}
+		}
+
+		Ok(patch)
+	}
+
+	fn get_metadata(&self) -> HashMap<String, String> {
+		get_rng_seed_metadata(self.options.rng_seed)
+	}
+}
+
+/// Finds the glyph of a character, at a given size
+fn get_glyph(font: &FontArc, character: char, font_size: f64) -> Glyph {
+	font.glyph_id(character).with_scale(PxScale::from(font_size.max(1.0) as f32))
+}
+
+/// Finds the size of the mask a glyph is rasterized into
+fn get_mask_dimensions(outlined_glyph: &OutlinedGlyph) -> (u32, u32) {
+	let bounds = outlined_glyph.px_bounds();
+	((bounds.width() as u32).max(1), (bounds.height() as u32).max(1))
+}
@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+
+use image::{Pixel, Rgb, RgbImage};
+
+use crate::generator::painter::shape::{Shape, ShapeDetails};
+use crate::generator::painter::{
+	get_random_shape, get_rng_seed_metadata, Painter, ShapeGeometry, ShapeOptions,
+};
+use crate::generator::utils::color::BlendingMode;
+use crate::generator::utils::fill::FillType;
+use crate::generator::utils::geom::{rect_extents, rotate_point, span_coverage};
+use crate::generator::utils::patch::Patch;
+use crate::generator::utils::pixel::blend;
+use crate::generator::utils::placement::ErrorMap;
+use crate::generator::utils::random::{
+	get_random_position, get_random_ranges_bias_weighted, get_random_size_ranges_bias_weighted,
+};
+use crate::generator::utils::units::{Margins, Rectangle, SizeUnit, WeightedValue};
+
+pub struct LinePainter {
+	pub options: Options,
+}
+
+pub struct Options {
+	pub blending_mode: Vec<WeightedValue<BlendingMode>>,
+	pub fill: Vec<WeightedValue<FillType>>,
+	pub alpha: Vec<WeightedValue<(f64, f64)>>,
+	pub alpha_bias: f64,
+	pub thickness: Vec<WeightedValue<(SizeUnit, SizeUnit)>>,
+	pub thickness_bias: f64, // 0 = normal; -1 = quad bias towards small, 1 = quad bias towards big, etc
+	pub length: Vec<WeightedValue<(SizeUnit, SizeUnit)>>,
+	pub length_bias: f64, // 0 = normal; -1 = quad bias towards small, 1 = quad bias towards big, etc
+	pub angle: Vec<WeightedValue<(f64, f64)>>, // In degrees
+	pub angle_bias: f64,
+	pub anti_alias: bool,
+	pub color_seed: f64,
+	pub rng_seed: u32,
+	pub margins: Margins<SizeUnit>,
+}
+
+impl LinePainter {
+	pub fn new() -> Self {
+		let options = Options {
+			blending_mode: vec![WeightedValue {
+				value: BlendingMode::default(),
+				weight: 1.0,
+			}],
+			fill: vec![WeightedValue {
+				value: FillType::default(),
+				weight: 1.0,
+			}],
+			alpha: vec![WeightedValue {
+				value: (1.0, 1.0),
+				weight: 1.0,
+			}],
+			alpha_bias: 0.0,
+			thickness: vec![WeightedValue {
+				value: (SizeUnit::Pixels(1), SizeUnit::Pixels(3)),
+				weight: 1.0,
+			}],
+			thickness_bias: 0.0,
+			length: vec![WeightedValue {
+				value: (SizeUnit::Fraction(0.05), SizeUnit::Fraction(0.3)),
+				weight: 1.0,
+			}],
+			length_bias: 0.0,
+			angle: vec![WeightedValue {
+				value: (0.0, 180.0),
+				weight: 1.0,
+			}],
+			angle_bias: 0.0,
+			anti_alias: true,
+			color_seed: 0.0,
+			rng_seed: 0,
+			margins: Margins::<SizeUnit> {
+				top: SizeUnit::Pixels(0),
+				right: SizeUnit::Pixels(0),
+				bottom: SizeUnit::Pixels(0),
+				left: SizeUnit::Pixels(0),
+			},
+		};
+
+		LinePainter {
+			options,
+		}
+	}
+}
+
+impl Painter for LinePainter {
+	fn get_shape(
+		&self,
+		canvas: &RgbImage,
+		iteration: u32,
+		seed_map: &RgbImage,
+		error_map: Option<&ErrorMap>,
+	) -> Result<Shape, &str> {
+		let options = ShapeOptions {
+			blending_mode: &self.options.blending_mode,
+			fill: &self.options.fill,
+			alpha: &self.options.alpha,
+			alpha_bias: self.options.alpha_bias,
+			color_seed: self.options.color_seed,
+			rng_seed: self.options.rng_seed,
+			margins: &self.options.margins,
+		};
+		get_random_shape(&options, canvas, iteration, seed_map, |rng, target_area, target_visible_area| {
+			// Find random dimensions for the line to be painted
+			let max_dimension = target_visible_area.0.min(target_visible_area.1);
+			let thickness = get_random_size_ranges_bias_weighted(
+				rng,
+				&self.options.thickness,
+				self.options.thickness_bias,
+				max_dimension,
+			);
+			let length = get_random_size_ranges_bias_weighted(
+				rng,
+				&self.options.length,
+				self.options.length_bias,
+				max_dimension,
+			);
+			let angle = get_random_ranges_bias_weighted(rng, &self.options.angle, self.options.angle_bias)
+				.to_radians();
+			let (extent_x, extent_y) = rect_extents(length, thickness, angle);
+
+			// Distribute along the axis too, keeping the whole line inside the target area
+			let (center_x, center_y) = get_random_position(
+				rng,
+				error_map,
+				(target_area.x as f64 + extent_x, (target_area.x + target_area.width) as f64 - extent_x),
+				(target_area.y as f64 + extent_y, (target_area.y + target_area.height) as f64 - extent_y),
+			);
+
+			ShapeGeometry {
+				x: center_x,
+				y: center_y,
+				seed_point: (center_x, center_y),
+				bounds: Rectangle {
+					x: center_x - extent_x,
+					y: center_y - extent_y,
+					width: extent_x * 2.0,
+					height: extent_y * 2.0,
+				},
+				details: ShapeDetails::Lines {
+					length,
+					thickness,
+					angle,
+				},
+			}
+		})
+	}
+
+	fn paint_shape(&self, canvas: &RgbImage, shape: &Shape) -> Result<Patch, &str> {
+		let (length, thickness, angle) = match shape.details {
+			ShapeDetails::Lines {
+				length,
+				thickness,
+				angle,
+			} => (length * shape.scale, thickness * shape.scale, angle),
+			_ => return Err("Cannot paint shape from another painter"),
+		};
+		let (center_x, center_y) = (shape.x, shape.y);
+		let image_area = canvas.dimensions();
+		let (extent_x, extent_y) = rect_extents(length, thickness, angle);
+
+		// Find final, round positions
+		let x1 = (center_x - extent_x).floor().max(0.0).min(image_area.0 as f64) as u32;
+		let x2 = (center_x + extent_x + 1.0).ceil().max(0.0).min(image_area.0 as f64) as u32;
+		let y1 = (center_y - extent_y).floor().max(0.0).min(image_area.1 as f64) as u32;
+		let y2 = (center_y + extent_y + 1.0).ceil().max(0.0).min(image_area.1 as f64) as u32;
+
+		// Finally, paint; test every pixel of the line bounds against the unrotated line
+		let mut patch = Patch::from_canvas(canvas, x1, y1, x2, y2);
+		for x in x1..x2 {
+			for y in y1..y2 {
+				let (local_x, local_y) = rotate_point(x as f64, y as f64, center_x, center_y, -angle);
+				let coverage = span_coverage(local_x, center_x - length / 2.0, center_x + length / 2.0)
+					* span_coverage(local_y, center_y - thickness / 2.0, center_y + thickness / 2.0);
+				let new_alpha = if self.options.anti_alias {
+					coverage
+				} else if coverage >= 0.5 {
+					1.0
+				} else {
+					0.0
+				};
+				if new_alpha > 0.0 {
+					let new_pixel = Rgb(blend(
+						patch.get_pixel(x, y).channels(),
+						&shape.get_color(x as f64, y as f64),
+						new_alpha * shape.alpha,
+						&shape.blending_mode,
+					));
+					patch.put_pixel(x, y, new_pixel);
+				}
+			}
+		}
+
+		Ok(patch)
+	}
+
+	fn get_metadata(&self) -> HashMap<String, String> {
+		get_rng_seed_metadata(self.options.rng_seed)
+	}
+}
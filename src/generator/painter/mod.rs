@@ -1,12 +1,148 @@
 use std::collections::HashMap;
 
 use image::RgbImage;
+use strum_macros::{Display, EnumString};
 
+use crate::generator::utils::color::BlendingMode;
+use crate::generator::utils::fill::{get_random_fill, FillType};
+use crate::generator::utils::geom::find_target_draw_rect;
+use crate::generator::utils::image::get_pixel_interpolated;
+use crate::generator::utils::patch::Patch;
+use crate::generator::utils::pixel::blend_linear;
+use crate::generator::utils::placement::ErrorMap;
+use crate::generator::utils::random::rng::Rng;
+use crate::generator::utils::random::{
+	get_random_color, get_random_entry_weighted, get_random_ranges_bias_weighted, get_rng,
+};
+use crate::generator::utils::units::{Margins, Rectangle, SizeUnit, WeightedValue};
+use shape::{Shape, ShapeDetails};
+
+pub mod brush;
 pub mod circle;
+pub mod ellipse;
+pub mod glyph;
+pub mod line;
+pub mod polygon;
 pub mod rect;
+pub mod shape;
+pub mod stamp;
 pub mod stroke;
+pub mod triangle;
+
+#[derive(Clone, Debug, Display, EnumString, PartialEq)]
+pub enum PainterType {
+	#[strum(serialize = "circles")]
+	Circles,
+	#[strum(serialize = "strokes")]
+	Strokes,
+	#[strum(serialize = "rects")]
+	Rects,
+	#[strum(serialize = "triangles")]
+	Triangles,
+	#[strum(serialize = "polygons")]
+	Polygons,
+	#[strum(serialize = "ellipses")]
+	Ellipses,
+	#[strum(serialize = "brushes")]
+	Brushes,
+	#[strum(serialize = "lines")]
+	Lines,
+	#[strum(serialize = "stamps")]
+	Stamps,
+	#[strum(serialize = "glyphs")]
+	Glyphs,
+}
 
 pub trait Painter {
-	fn paint(&self, canvas: &RgbImage, iteration: u32, seed_map: &RgbImage) -> Result<RgbImage, &str>;
+	/// Picks the parameters of a new random element.
+	fn get_shape(
+		&self,
+		canvas: &RgbImage,
+		iteration: u32,
+		seed_map: &RgbImage,
+		error_map: Option<&ErrorMap>,
+	) -> Result<Shape, &str>;
+	/// Paints an element picked by this painter over the canvas, returning the area it changes.
+	fn paint_shape(&self, canvas: &RgbImage, shape: &Shape) -> Result<Patch, &str>;
 	fn get_metadata(&self) -> HashMap<String, String>;
 }
+
+/// The options shared by all painters, used to pick everything about a new element except its geometry.
+pub struct ShapeOptions<'a> {
+	pub blending_mode: &'a Vec<WeightedValue<BlendingMode>>,
+	pub fill: &'a Vec<WeightedValue<FillType>>,
+	pub alpha: &'a Vec<WeightedValue<(f64, f64)>>,
+	pub alpha_bias: f64,
+	pub color_seed: f64,
+	pub rng_seed: u32,
+	pub margins: &'a Margins<SizeUnit>,
+}
+
+/// The geometry of a new element, as picked by its painter.
+pub struct ShapeGeometry {
+	pub x: f64,
+	pub y: f64,
+	/// The point of the seed map the color of the element is picked from
+	pub seed_point: (f64, f64),
+	/// The box covered by the element
+	pub bounds: Rectangle<f64>,
+	pub details: ShapeDetails,
+}
+
+/// Picks a new random element. `get_geometry` picks its geometry inside the target area (also given
+/// clipped to the canvas), and the color, opacity, blending mode, and fill are then picked the same way
+/// for all painters.
+pub fn get_random_shape(
+	options: &ShapeOptions,
+	canvas: &RgbImage,
+	iteration: u32,
+	seed_map: &RgbImage,
+	get_geometry: impl FnOnce(&mut Rng, &Rectangle<i64>, (u32, u32)) -> ShapeGeometry,
+) -> Result<Shape, &'static str> {
+	let mut rng = get_rng(options.rng_seed, iteration);
+
+	let image_area = canvas.dimensions();
+	let target_area = find_target_draw_rect(image_area, options.margins)?;
+	let target_visible_area =
+		(image_area.0.min(target_area.width as u32), image_area.1.min(target_area.height as u32));
+
+	let geometry = get_geometry(&mut rng, &target_area, target_visible_area);
+
+	// Determine color
+	let random_color = get_random_color(&mut rng);
+	let seed_color = get_pixel_interpolated(seed_map, geometry.seed_point.0, geometry.seed_point.1);
+	let color = blend_linear(&random_color, &seed_color, options.color_seed);
+	let alpha = get_random_ranges_bias_weighted(&mut rng, options.alpha, options.alpha_bias);
+
+	// Decide on blending mode
+	let blending_mode = get_random_entry_weighted(&mut rng, options.blending_mode);
+
+	// Decide on fill
+	let fill = get_random_fill(
+		&mut rng,
+		options.fill,
+		&color,
+		&random_color,
+		seed_map,
+		options.color_seed,
+		&geometry.bounds,
+	);
+
+	Ok(Shape::new(
+		geometry.x,
+		geometry.y,
+		alpha,
+		blending_mode.clone(),
+		fill,
+		target_area,
+		&geometry.bounds,
+		geometry.details,
+	))
+}
+
+/// The metadata of painters that only need their seed to be reproduced.
+pub fn get_rng_seed_metadata(rng_seed: u32) -> HashMap<String, String> {
+	let mut data = HashMap::new();
+	data.insert(String::from("RNG seed"), format!("{}", rng_seed));
+	data
+}
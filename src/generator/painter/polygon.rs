@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+use std::f64::consts::PI;
+
+use image::{Pixel, Rgb, RgbImage};
+
+use crate::generator::painter::shape::{Shape, ShapeDetails};
+use crate::generator::painter::{
+	get_random_shape, get_rng_seed_metadata, Painter, ShapeGeometry, ShapeOptions,
+};
+use crate::generator::utils::color::BlendingMode;
+use crate::generator::utils::fill::FillType;
+use crate::generator::utils::geom::{get_edge_alpha, polygon_edge_distance, polygon_scanline_spans};
+use crate::generator::utils::patch::Patch;
+use crate::generator::utils::pixel::blend;
+use crate::generator::utils::placement::ErrorMap;
+use crate::generator::utils::random::{
+	get_random_integer_ranges_weighted, get_random_position, get_random_range,
+	get_random_ranges_bias_weighted, get_random_size_ranges_bias_weighted,
+};
+use crate::generator::utils::units::{Margins, Rectangle, SizeUnit, WeightedValue};
+
+pub struct PolygonPainter {
+	pub options: Options,
+}
+
+pub struct Options {
+	pub blending_mode: Vec<WeightedValue<BlendingMode>>,
+	pub fill: Vec<WeightedValue<FillType>>,
+	pub alpha: Vec<WeightedValue<(f64, f64)>>,
+	pub alpha_bias: f64,
+	pub vertices: Vec<WeightedValue<(u32, u32)>>,
+	pub radius: Vec<WeightedValue<(SizeUnit, SizeUnit)>>,
+	pub radius_bias: f64, // 0 = normal; -1 = quad bias towards small, 1 = quad bias towards big, etc
+	pub irregularity: Vec<WeightedValue<(f64, f64)>>, // 0 = regular polygon; 1 = fully random angles and radii
+	pub irregularity_bias: f64,
+	pub anti_alias: bool,
+	pub color_seed: f64,
+	pub rng_seed: u32,
+	pub margins: Margins<SizeUnit>,
+}
+
+impl PolygonPainter {
+	pub fn new() -> Self {
+		let options = Options {
+			blending_mode: vec![WeightedValue {
+				value: BlendingMode::default(),
+				weight: 1.0,
+			}],
+			fill: vec![WeightedValue {
+				value: FillType::default(),
+				weight: 1.0,
+			}],
+			alpha: vec![WeightedValue {
+				value: (1.0, 1.0),
+				weight: 1.0,
+			}],
+			alpha_bias: 0.0,
+			vertices: vec![WeightedValue {
+				value: (3, 8),
+				weight: 1.0,
+			}],
+			radius: vec![WeightedValue {
+				value: (SizeUnit::Fraction(0.0), SizeUnit::Fraction(0.5)),
+				weight: 1.0,
+			}],
+			radius_bias: 0.0,
+			irregularity: vec![WeightedValue {
+				value: (0.5, 0.5),
+				weight: 1.0,
+			}],
+			irregularity_bias: 0.0,
+			anti_alias: true,
+			color_seed: 0.0,
+			rng_seed: 0,
+			margins: Margins::<SizeUnit> {
+				top: SizeUnit::Pixels(0),
+				right: SizeUnit::Pixels(0),
+				bottom: SizeUnit::Pixels(0),
+				left: SizeUnit::Pixels(0),
+			},
+		};
+
+		PolygonPainter {
+			options,
+		}
+	}
+}
+
+impl Painter for PolygonPainter {
+	fn get_shape(
+		&self,
+		canvas: &RgbImage,
+		iteration: u32,
+		seed_map: &RgbImage,
+		error_map: Option<&ErrorMap>,
+	) -> Result<Shape, &str> {
+		let options = ShapeOptions {
+			blending_mode: &self.options.blending_mode,
+			fill: &self.options.fill,
+			alpha: &self.options.alpha,
+			alpha_bias: self.options.alpha_bias,
+			color_seed: self.options.color_seed,
+			rng_seed: self.options.rng_seed,
+			margins: &self.options.margins,
+		};
+		get_random_shape(&options, canvas, iteration, seed_map, |rng, target_area, target_visible_area| {
+			// Find random outer radius for the polygon to be painted
+			let max_dimension = target_visible_area.0.min(target_visible_area.1);
+			let radius = get_random_size_ranges_bias_weighted(
+				rng,
+				&self.options.radius,
+				self.options.radius_bias,
+				max_dimension,
+			);
+
+			// Distribute along the axis too
+			let (center_x, center_y) = get_random_position(
+				rng,
+				error_map,
+				(target_area.x as f64 + radius, (target_area.x + target_area.width) as f64 - radius),
+				(target_area.y as f64 + radius, (target_area.y + target_area.height) as f64 - radius),
+			);
+
+			// Create the vertices around the center; irregularity shifts their angles and shrinks their
+			// distance to the center, which creates star-like or blob-like shapes
+			let num_vertices = get_random_integer_ranges_weighted(rng, &self.options.vertices).max(3);
+			let irregularity = get_random_ranges_bias_weighted(
+				rng,
+				&self.options.irregularity,
+				self.options.irregularity_bias,
+			)
+			.clamp(0.0, 1.0);
+			let angle_step = PI * 2.0 / num_vertices as f64;
+			let angle_start = get_random_range(rng, 0.0, PI * 2.0);
+			let vertices: Vec<(f64, f64)> = (0..num_vertices)
+				.map(|i| {
+					let angle_offset = get_random_range(rng, -0.5, 0.5) * angle_step * irregularity;
+					let angle = angle_start + i as f64 * angle_step + angle_offset;
+					let vertex_radius = radius * (1.0 - get_random_range(rng, 0.0, irregularity));
+					(center_x + angle.cos() * vertex_radius, center_y + angle.sin() * vertex_radius)
+				})
+				.collect();
+
+			ShapeGeometry {
+				x: center_x,
+				y: center_y,
+				seed_point: (center_x, center_y),
+				bounds: Rectangle {
+					x: center_x - radius,
+					y: center_y - radius,
+					width: radius * 2.0,
+					height: radius * 2.0,
+				},
+				details: ShapeDetails::Polygons {
+					vertices,
+					radius,
+				},
+			}
+		})
+	}
+
+	fn paint_shape(&self, canvas: &RgbImage, shape: &Shape) -> Result<Patch, &str> {
+		let (vertices, radius): (Vec<(f64, f64)>, f64) = match &shape.details {
+			ShapeDetails::Polygons {
+				vertices,
+				radius,
+			} => {
+				(vertices.iter().map(|&vertex| shape.transform_point(vertex)).collect(), radius * shape.scale)
+			}
+			_ => return Err("Cannot paint shape from another painter"),
+		};
+		let (center_x, center_y) = (shape.x, shape.y);
+		let image_area = canvas.dimensions();
+
+		// Find final, round positions
+		let x1 = (center_x - radius).floor().max(0.0).min(image_area.0 as f64) as u32;
+		let x2 = (center_x + radius).ceil().max(0.0).min(image_area.0 as f64) as u32;
+		let y1 = (center_y - radius).floor().max(0.0).min(image_area.1 as f64) as u32;
+		let y2 = (center_y + radius).ceil().max(0.0).min(image_area.1 as f64) as u32;
+
+		// Finally, paint, one scanline at a time
+		let mut patch = Patch::from_canvas(canvas, x1, y1, x2, y2);
+		for y in y1..y2 {
+			for (span_start, span_end) in polygon_scanline_spans(y as f64, &vertices) {
+				let span_x1 = span_start.ceil().max(0.0).min(image_area.0 as f64) as u32;
+				let span_x2 = span_end.ceil().max(0.0).min(image_area.0 as f64) as u32;
+				for x in span_x1..span_x2 {
+					let dist = polygon_edge_distance(x as f64, y as f64, &vertices);
+					let new_alpha = get_edge_alpha(dist, self.options.anti_alias);
+					let new_pixel = Rgb(blend(
+						patch.get_pixel(x, y).channels(),
+						&shape.get_color(x as f64, y as f64),
+						new_alpha * shape.alpha,
+						&shape.blending_mode,
+					));
+					patch.put_pixel(x, y, new_pixel);
+				}
+			}
+		}
+
+		Ok(patch)
+	}
+
+	fn get_metadata(&self) -> HashMap<String, String> {
+		get_rng_seed_metadata(self.options.rng_seed)
+	}
+}
@@ -2,15 +2,19 @@ use std::collections::HashMap;
 
 use image::{Pixel, Rgb, RgbImage};
 
+use crate::generator::painter::shape::{Shape, ShapeDetails};
 use crate::generator::painter::Painter;
 use crate::generator::utils::color::BlendingMode;
-use crate::generator::utils::geom::find_target_draw_rect;
+use crate::generator::utils::fill::{get_random_fill, FillType};
+use crate::generator::utils::geom::{find_target_draw_rect, rect_coverage, rect_extents, rotate_point};
+use crate::generator::utils::patch::Patch;
 use crate::generator::utils::pixel::{blend, blend_linear};
+use crate::generator::utils::placement::ErrorMap;
 use crate::generator::utils::random::{
-	get_random_entry_weighted, get_random_range, get_random_ranges_bias_weighted,
-	get_random_size_ranges_bias_weighted, get_rng,
+	get_random_entry_weighted, get_random_position, get_random_ranges_bias_weighted,
+	get_random_ranges_bias_weighted_lazy, get_random_size_ranges_bias_weighted, get_rng,
 };
-use crate::generator::utils::units::{Margins, SizeUnit, WeightedValue};
+use crate::generator::utils::units::{Margins, Rectangle, SizeUnit, WeightedValue};
 use crate::generator::utils::{image::get_pixel_interpolated, random::get_random_color};
 
 pub struct RectPainter {
@@ -19,12 +23,16 @@ pub struct RectPainter {
 
 pub struct Options {
 	pub blending_mode: Vec<WeightedValue<BlendingMode>>,
+	pub fill: Vec<WeightedValue<FillType>>,
 	pub alpha: Vec<WeightedValue<(f64, f64)>>,
 	pub alpha_bias: f64,
 	pub width: Vec<WeightedValue<(SizeUnit, SizeUnit)>>,
 	pub height: Vec<WeightedValue<(SizeUnit, SizeUnit)>>,
 	pub width_bias: f64, // 0 = normal; -1 = quad bias towards small, 1 = quad bias towards big, etc
 	pub height_bias: f64, // 0 = normal; -1 = quad bias towards small, 1 = quad bias towards big, etc
+	pub rotation: Vec<WeightedValue<(f64, f64)>>, // In degrees
+	pub rotation_bias: f64,
+	pub anti_alias: bool,
 	pub color_seed: f64,
 	pub rng_seed: u32,
 	pub margins: Margins<SizeUnit>,
@@ -37,6 +45,10 @@ impl RectPainter {
 				value: BlendingMode::default(),
 				weight: 1.0,
 			}],
+			fill: vec![WeightedValue {
+				value: FillType::default(),
+				weight: 1.0,
+			}],
 			alpha: vec![WeightedValue {
 				value: (1.0, 1.0),
 				weight: 1.0,
@@ -52,6 +64,12 @@ impl RectPainter {
 				weight: 1.0,
 			}],
 			height_bias: 0.0,
+			rotation: vec![WeightedValue {
+				value: (0.0, 0.0),
+				weight: 1.0,
+			}],
+			rotation_bias: 0.0,
+			anti_alias: true,
 			color_seed: 0.0,
 			rng_seed: 0,
 			margins: Margins::<SizeUnit> {
@@ -69,7 +87,13 @@ impl RectPainter {
 }
 
 impl Painter for RectPainter {
-	fn paint(&self, canvas: &RgbImage, iteration: u32, seed_map: &RgbImage) -> Result<RgbImage, &str> {
+	fn get_shape(
+		&self,
+		canvas: &RgbImage,
+		iteration: u32,
+		seed_map: &RgbImage,
+		error_map: Option<&ErrorMap>,
+	) -> Result<Shape, &str> {
 		let mut rng = get_rng(self.options.rng_seed, iteration);
 
 		let image_area = canvas.dimensions();
@@ -94,23 +118,25 @@ impl Painter for RectPainter {
 			target_visible_area.1,
 		);
 
-		// Distribute along the axis too
-		let rect_x = get_random_range(
+		// Rotate it, if needed
+		let rotation = get_random_ranges_bias_weighted_lazy(
 			&mut rng,
-			target_area.x as f64,
-			(target_area.x + target_area.width) as f64 - rect_w,
-		);
-		let rect_y = get_random_range(
+			&self.options.rotation,
+			self.options.rotation_bias,
+		)
+		.to_radians();
+		let (extent_x, extent_y) = rect_extents(rect_w, rect_h, rotation);
+
+		// Distribute along the axis too, keeping the whole rotated rect inside the target area
+		let (rect_center_x, rect_center_y) = get_random_position(
 			&mut rng,
-			target_area.y as f64,
-			(target_area.y + target_area.height) as f64 - rect_h,
+			error_map,
+			(target_area.x as f64 + extent_x, (target_area.x + target_area.width) as f64 - extent_x),
+			(target_area.y as f64 + extent_y, (target_area.y + target_area.height) as f64 - extent_y),
 		);
-
-		// Find final, round positions
-		let x1 = rect_x.round().max(0.0).min(image_area.0 as f64) as u32;
-		let x2 = (rect_x + rect_w).round().max(0.0).min(image_area.0 as f64) as u32;
-		let y1 = rect_y.round().max(0.0).min(image_area.1 as f64) as u32;
-		let y2 = (rect_y + rect_h).round().max(0.0).min(image_area.1 as f64) as u32;
+		let rect_x = rect_center_x - rect_w / 2.0;
+		let rect_y = rect_center_y - rect_h / 2.0;
+		let (x1, y1, x2, y2) = get_rect_bounds(rect_center_x, rect_center_y, rect_w, rect_h, image_area);
 
 		// Determine color
 		let random_color = get_random_color(&mut rng);
@@ -121,17 +147,108 @@ impl Painter for RectPainter {
 		// Decide on blending mode
 		let blending_mode = get_random_entry_weighted(&mut rng, &self.options.blending_mode);
 
+		// Decide on fill
+		let bounds = Rectangle {
+			x: rect_x + rect_w / 2.0 - extent_x,
+			y: rect_y + rect_h / 2.0 - extent_y,
+			width: extent_x * 2.0,
+			height: extent_y * 2.0,
+		};
+		let fill = get_random_fill(
+			&mut rng,
+			&self.options.fill,
+			&color,
+			&random_color,
+			seed_map,
+			self.options.color_seed,
+			&bounds,
+		);
+
+		Ok(Shape::new(
+			rect_center_x,
+			rect_center_y,
+			alpha,
+			blending_mode.clone(),
+			fill,
+			target_area,
+			&bounds,
+			ShapeDetails::Rects {
+				width: rect_w,
+				height: rect_h,
+				rotation,
+			},
+		))
+	}
+
+	fn paint_shape(&self, canvas: &RgbImage, shape: &Shape) -> Result<Patch, &str> {
+		let (rect_w, rect_h, rotation) = match shape.details {
+			ShapeDetails::Rects {
+				width,
+				height,
+				rotation,
+			} => (width * shape.scale, height * shape.scale, rotation),
+			_ => return Err("Cannot paint shape from another painter"),
+		};
+		let image_area = canvas.dimensions();
+
 		// Finally, paint
-		let mut painted_canvas = canvas.clone();
-		for x in x1..x2 {
-			for y in y1..y2 {
-				let new_pixel =
-					Rgb(blend(painted_canvas.get_pixel(x, y).channels(), &color, alpha, &blending_mode));
-				painted_canvas.put_pixel(x, y, new_pixel);
+		let patch = if rotation == 0.0 {
+			// Fast path, axis-aligned
+			let (x1, y1, x2, y2) = get_rect_bounds(shape.x, shape.y, rect_w, rect_h, image_area);
+			let mut patch = Patch::from_canvas(canvas, x1, y1, x2, y2);
+			for x in x1..x2 {
+				for y in y1..y2 {
+					let new_pixel = Rgb(blend(
+						patch.get_pixel(x, y).channels(),
+						&shape.get_color(x as f64, y as f64),
+						shape.alpha,
+						&shape.blending_mode,
+					));
+					patch.put_pixel(x, y, new_pixel);
+				}
 			}
-		}
+			patch
+		} else {
+			// Slow path, rotated; test every pixel of the rotated bounds against the original rect.
+			// Pixel centers are at round coordinates, so the rect edges are half a pixel outside of them.
+			// The rect itself is never clipped to the image, only the patch it's painted into
+			let center_x = shape.x - 0.5;
+			let center_y = shape.y - 0.5;
+			let (edge_x1, edge_y1) = (center_x - rect_w / 2.0, center_y - rect_h / 2.0);
+			let (edge_x2, edge_y2) = (center_x + rect_w / 2.0, center_y + rect_h / 2.0);
+			let (extent_x, extent_y) = rect_extents(rect_w, rect_h, rotation);
+			let x1_rotated = (center_x - extent_x).floor().max(0.0).min(image_area.0 as f64) as u32;
+			let x2_rotated = (center_x + extent_x + 1.0).ceil().max(0.0).min(image_area.0 as f64) as u32;
+			let y1_rotated = (center_y - extent_y).floor().max(0.0).min(image_area.1 as f64) as u32;
+			let y2_rotated = (center_y + extent_y + 1.0).ceil().max(0.0).min(image_area.1 as f64) as u32;
 
-		Ok(painted_canvas)
+			let mut patch = Patch::from_canvas(canvas, x1_rotated, y1_rotated, x2_rotated, y2_rotated);
+			for x in x1_rotated..x2_rotated {
+				for y in y1_rotated..y2_rotated {
+					let (local_x, local_y) = rotate_point(x as f64, y as f64, center_x, center_y, -rotation);
+					let coverage = rect_coverage(local_x, local_y, edge_x1, edge_y1, edge_x2, edge_y2);
+					let new_alpha = if self.options.anti_alias {
+						coverage
+					} else if coverage >= 0.5 {
+						1.0
+					} else {
+						0.0
+					};
+					if new_alpha > 0.0 {
+						let new_pixel = Rgb(blend(
+							patch.get_pixel(x, y).channels(),
+							&shape.get_color(x as f64, y as f64),
+							new_alpha * shape.alpha,
+							&shape.blending_mode,
+						));
+						patch.put_pixel(x, y, new_pixel);
+					}
+				}
+			}
+			patch
+		};
+
+		Ok(patch)
 	}
 
 	fn get_metadata(&self) -> HashMap<String, String> {
@@ -140,3 +257,21 @@ impl Painter for RectPainter {
 		data
 	}
 }
+
+/// Finds the final, round pixel positions of an unrotated rect, clipped to the image
+fn get_rect_bounds(
+	center_x: f64,
+	center_y: f64,
+	width: f64,
+	height: f64,
+	image_area: (u32, u32),
+) -> (u32, u32, u32, u32) {
+	let rect_x = center_x - width / 2.0;
+	let rect_y = center_y - height / 2.0;
+	(
+		rect_x.round().max(0.0).min(image_area.0 as f64) as u32,
+		rect_y.round().max(0.0).min(image_area.1 as f64) as u32,
+		(rect_x + width).round().max(0.0).min(image_area.0 as f64) as u32,
+		(rect_y + height).round().max(0.0).min(image_area.1 as f64) as u32,
+	)
+}
@@ -0,0 +1,209 @@
+use crate::generator::utils::color::BlendingMode;
+use crate::generator::utils::fill::Fill;
+use crate::generator::utils::random::get_random_range;
+use crate::generator::utils::random::rng::Rng;
+use crate::generator::utils::units::Rectangle;
+
+// How much each change to a shape can move, resize, recolor, or change the opacity of it
+const MUTATION_POSITION: f64 = 0.05; // Relative to the size of the area it can be placed in
+const MUTATION_SCALE: f64 = 0.2;
+const MUTATION_COLOR: f64 = 32.0;
+const MUTATION_ALPHA: f64 = 0.1;
+const MIN_SCALE: f64 = 0.05;
+
+/// The parameters of an element picked by a painter, so it can be changed and painted again.
+///
+/// Painters keep the size and geometry they picked in `details`; the element is then moved so its
+/// center is at `x`/`y`, and resized by `scale`.
+#[derive(Clone, Debug)]
+pub struct Shape {
+	pub x: f64,
+	pub y: f64,
+	pub scale: f64,
+	pub alpha: f64,
+	pub blending_mode: BlendingMode,
+	pub fill: Fill,
+	pub details: ShapeDetails,
+	area: Rectangle<i64>,
+	origin: (f64, f64),
+	max_scale: f64,
+}
+
+/// Painter-specific parameters of an element, at the size the element was first picked at. Positions are
+/// in canvas coordinates, except for the points of `Brushes`, which are relative to the element's center.
+#[derive(Clone, Debug)]
+pub enum ShapeDetails {
+	Brushes {
+		points: [(f64, f64); 4],
+		thickness: f64,
+		taper: f64,
+		length: f64,
+	},
+	Circles {
+		radius: f64,
+	},
+	Ellipses {
+		radius_x: f64,
+		radius_y: f64,
+		rotation: f64,
+	},
+	Glyphs {
+		character: char,
+		font_size: f64,
+		rotation: f64,
+	},
+	Lines {
+		length: f64,
+		thickness: f64,
+		angle: f64,
+	},
+	Polygons {
+		vertices: Vec<(f64, f64)>,
+		radius: f64,
+	},
+	Rects {
+		width: f64,
+		height: f64,
+		rotation: f64,
+	},
+	Stamps {
+		index: usize,
+		scale: f64,
+		rotation: f64,
+	},
+	Strokes {
+		width: f64,
+		height: f64,
+		rotation: f64,
+		wave_height: f64,
+		wave_length: f64,
+		noise: Option<Box<[f64; 256]>>,
+	},
+	Triangles {
+		vertices: [(f64, f64); 3],
+	},
+}
+
+impl Shape {
+	/// Creates a shape centered at (x, y), whose center can later be moved anywhere inside `area`. `bounds`
+	/// is the box the shape covers, and limits how much it can grow.
+	#[allow(clippy::too_many_arguments)]
+	pub fn new(
+		x: f64,
+		y: f64,
+		alpha: f64,
+		blending_mode: BlendingMode,
+		fill: Fill,
+		area: Rectangle<i64>,
+		bounds: &Rectangle<f64>,
+		details: ShapeDetails,
+	) -> Shape {
+		// A shape can grow until it's as big as the area it can be placed in, but never has to shrink
+		let max_scale = (area.width.max(area.height) as f64 / bounds.width.max(bounds.height)).max(1.0);
+		Shape {
+			x,
+			y,
+			scale: 1.0,
+			alpha,
+			blending_mode,
+			fill,
+			details,
+			area,
+			origin: (x, y),
+			max_scale,
+		}
+	}
+
+	/// Whether the shape is still where, and how big, it was first picked.
+	fn is_unchanged(&self) -> bool {
+		(self.x, self.y) == self.origin && self.scale == 1.0
+	}
+
+	/// Moves a point picked along with the shape to where it is now.
+	#[inline(always)]
+	pub fn transform_point(&self, point: (f64, f64)) -> (f64, f64) {
+		if self.is_unchanged() {
+			point
+		} else {
+			(self.x + (point.0 - self.origin.0) * self.scale, self.y + (point.1 - self.origin.1) * self.scale)
+		}
+	}
+
+	/// Finds the color of the shape's fill at a given pixel, following the shape as it moves.
+	#[inline(always)]
+	pub fn get_color(&self, x: f64, y: f64) -> [u8; 3] {
+		if self.is_unchanged() {
+			self.fill.get_color(x, y)
+		} else {
+			self.fill.get_color(
+				self.origin.0 + (x - self.x) / self.scale,
+				self.origin.1 + (y - self.y) / self.scale,
+			)
+		}
+	}
+
+	/// Randomly changes either the position, the size, the color, or the opacity of the shape.
+	pub fn mutate(&mut self, rng: &mut Rng) {
+		match rng.next_u32_range(0, 4) {
+			0 => {
+				let (x1, y1) = (self.area.x as f64, self.area.y as f64);
+				let (x2, y2) = (x1 + self.area.width as f64, y1 + self.area.height as f64);
+				self.x =
+					(self.x + get_random_range(rng, -1.0, 1.0) * (x2 - x1) * MUTATION_POSITION).clamp(x1, x2);
+				self.y =
+					(self.y + get_random_range(rng, -1.0, 1.0) * (y2 - y1) * MUTATION_POSITION).clamp(y1, y2);
+			}
+			1 => {
+				self.scale = (self.scale * (1.0 + get_random_range(rng, -1.0, 1.0) * MUTATION_SCALE))
+					.clamp(MIN_SCALE, self.max_scale);
+			}
+			2 => {
+				for color in self.fill.colors_mut() {
+					for channel in color.iter_mut() {
+						let change = get_random_range(rng, -1.0, 1.0) * MUTATION_COLOR;
+						*channel = (*channel as f64 + change).round().clamp(0.0, 255.0) as u8;
+					}
+				}
+			}
+			_ => {
+				self.alpha = (self.alpha + get_random_range(rng, -1.0, 1.0) * MUTATION_ALPHA).clamp(0.0, 1.0);
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_mutate_keeps_scale_within_area() {
+		let mut shape = Shape::new(
+			50.0,
+			50.0,
+			1.0,
+			BlendingMode::default(),
+			Fill::Flat([0, 0, 0]),
+			Rectangle {
+				x: 0,
+				y: 0,
+				width: 100,
+				height: 100,
+			},
+			&Rectangle {
+				x: 40.0,
+				y: 40.0,
+				width: 20.0,
+				height: 20.0,
+			},
+			ShapeDetails::Circles {
+				radius: 10.0,
+			},
+		);
+		let mut rng = Rng::from_seed(1);
+		for _ in 0..10000 {
+			shape.mutate(&mut rng);
+			assert!(shape.scale >= MIN_SCALE && shape.scale <= 5.0);
+		}
+	}
+}
@@ -0,0 +1,209 @@
+use std::collections::HashMap;
+
+use image::{GrayImage, Pixel, Rgb, RgbImage};
+
+use crate::generator::painter::shape::{Shape, ShapeDetails};
+use crate::generator::painter::{
+	get_random_shape, get_rng_seed_metadata, Painter, ShapeGeometry, ShapeOptions,
+};
+use crate::generator::utils::color::BlendingMode;
+use crate::generator::utils::fill::FillType;
+use crate::generator::utils::geom::{rect_extents, rotate_point};
+use crate::generator::utils::image::{get_mask_value_interpolated, resize};
+use crate::generator::utils::patch::Patch;
+use crate::generator::utils::pixel::blend;
+use crate::generator::utils::placement::ErrorMap;
+use crate::generator::utils::random::{
+	get_random_index_weighted, get_random_position, get_random_ranges_bias_weighted,
+};
+use crate::generator::utils::units::{Margins, Rectangle, SizeUnit, WeightedValue};
+
+pub struct StampPainter {
+	pub options: Options,
+}
+
+pub struct Options {
+	pub blending_mode: Vec<WeightedValue<BlendingMode>>,
+	pub fill: Vec<WeightedValue<FillType>>,
+	pub alpha: Vec<WeightedValue<(f64, f64)>>,
+	pub alpha_bias: f64,
+	pub stamps: Vec<WeightedValue<GrayImage>>, // Alpha masks; see `image::to_alpha_mask()`
+	pub scale: Vec<WeightedValue<(f64, f64)>>, // Relative to the original size of the stamp image
+	pub scale_bias: f64,
+	pub rotation: Vec<WeightedValue<(f64, f64)>>, // In degrees
+	pub rotation_bias: f64,
+	pub color_seed: f64,
+	pub rng_seed: u32,
+	pub margins: Margins<SizeUnit>,
+}
+
+impl StampPainter {
+	pub fn new() -> Self {
+		let options = Options {
+			blending_mode: vec![WeightedValue {
+				value: BlendingMode::default(),
+				weight: 1.0,
+			}],
+			fill: vec![WeightedValue {
+				value: FillType::default(),
+				weight: 1.0,
+			}],
+			alpha: vec![WeightedValue {
+				value: (1.0, 1.0),
+				weight: 1.0,
+			}],
+			alpha_bias: 0.0,
+			stamps: vec![],
+			scale: vec![WeightedValue {
+				value: (1.0, 1.0),
+				weight: 1.0,
+			}],
+			scale_bias: 0.0,
+			rotation: vec![WeightedValue {
+				value: (0.0, 0.0),
+				weight: 1.0,
+			}],
+			rotation_bias: 0.0,
+			color_seed: 0.0,
+			rng_seed: 0,
+			margins: Margins::<SizeUnit> {
+				top: SizeUnit::Pixels(0),
+				right: SizeUnit::Pixels(0),
+				bottom: SizeUnit::Pixels(0),
+				left: SizeUnit::Pixels(0),
+			},
+		};
+
+		StampPainter {
+			options,
+		}
+	}
+}
+
+impl Painter for StampPainter {
+	fn get_shape(
+		&self,
+		canvas: &RgbImage,
+		iteration: u32,
+		seed_map: &RgbImage,
+		error_map: Option<&ErrorMap>,
+	) -> Result<Shape, &str> {
+		if self.options.stamps.is_empty() {
+			return Err("No stamp images to paint with");
+		}
+
+		let options = ShapeOptions {
+			blending_mode: &self.options.blending_mode,
+			fill: &self.options.fill,
+			alpha: &self.options.alpha,
+			alpha_bias: self.options.alpha_bias,
+			color_seed: self.options.color_seed,
+			rng_seed: self.options.rng_seed,
+			margins: &self.options.margins,
+		};
+		get_random_shape(&options, canvas, iteration, seed_map, |rng, target_area, _| {
+			// Pick a stamp, and find its final scale
+			let index = get_random_index_weighted(rng, &self.options.stamps);
+			let stamp = &self.options.stamps[index].value;
+			let scale = get_random_ranges_bias_weighted(rng, &self.options.scale, self.options.scale_bias);
+			let stamp_w = ((stamp.width() as f64 * scale).round() as u32).max(1);
+			let stamp_h = ((stamp.height() as f64 * scale).round() as u32).max(1);
+
+			// Rotate it
+			let rotation =
+				get_random_ranges_bias_weighted(rng, &self.options.rotation, self.options.rotation_bias)
+					.to_radians();
+			let (extent_x, extent_y) = rect_extents(stamp_w as f64, stamp_h as f64, rotation);
+
+			// Distribute along the axis too, keeping the whole rotated stamp inside the target area
+			let (center_x, center_y) = get_random_position(
+				rng,
+				error_map,
+				(target_area.x as f64 + extent_x, (target_area.x + target_area.width) as f64 - extent_x),
+				(target_area.y as f64 + extent_y, (target_area.y + target_area.height) as f64 - extent_y),
+			);
+
+			ShapeGeometry {
+				x: center_x,
+				y: center_y,
+				seed_point: (center_x, center_y),
+				bounds: Rectangle {
+					x: center_x - extent_x,
+					y: center_y - extent_y,
+					width: extent_x * 2.0,
+					height: extent_y * 2.0,
+				},
+				details: ShapeDetails::Stamps {
+					index,
+					scale,
+					rotation,
+				},
+			}
+		})
+	}
+
+	fn paint_shape(&self, canvas: &RgbImage, shape: &Shape) -> Result<Patch, &str> {
+		let (stamp, scale, rotation) = match shape.details {
+			ShapeDetails::Stamps {
+				index,
+				scale,
+				rotation,
+			} => match self.options.stamps.get(index) {
+				Some(stamp) => (&stamp.value, scale * shape.scale, rotation),
+				None => return Err("No stamp image to paint with"),
+			},
+			_ => return Err("Cannot paint shape from another painter"),
+		};
+		let (center_x, center_y) = (shape.x, shape.y);
+		let image_area = canvas.dimensions();
+
+		// Resize the stamp to its final scale
+		let stamp_w = ((stamp.width() as f64 * scale).round() as u32).max(1);
+		let stamp_h = ((stamp.height() as f64 * scale).round() as u32).max(1);
+		let resized_stamp;
+		let mask = if (stamp_w, stamp_h) == stamp.dimensions() {
+			stamp
+		} else {
+			resized_stamp = resize(stamp, stamp_w, stamp_h);
+			&resized_stamp
+		};
+		let (extent_x, extent_y) = rect_extents(stamp_w as f64, stamp_h as f64, rotation);
+
+		// Find final, round positions
+		let x1 = (center_x - extent_x).floor().max(0.0).min(image_area.0 as f64) as u32;
+		let x2 = (center_x + extent_x + 1.0).ceil().max(0.0).min(image_area.0 as f64) as u32;
+		let y1 = (center_y - extent_y).floor().max(0.0).min(image_area.1 as f64) as u32;
+		let y2 = (center_y + extent_y + 1.0).ceil().max(0.0).min(image_area.1 as f64) as u32;
+
+		// Finally, paint; every pixel of the rotated bounds reads from the mask at its unrotated position.
+		// Mask pixel centers are at round coordinates, so the center of the mask is half a pixel inside
+		let mask_center_x = stamp_w as f64 / 2.0 - 0.5;
+		let mask_center_y = stamp_h as f64 / 2.0 - 0.5;
+		let mut patch = Patch::from_canvas(canvas, x1, y1, x2, y2);
+		for x in x1..x2 {
+			for y in y1..y2 {
+				let (local_x, local_y) = rotate_point(x as f64, y as f64, center_x, center_y, -rotation);
+				let new_alpha = get_mask_value_interpolated(
+					mask,
+					local_x - center_x + mask_center_x,
+					local_y - center_y + mask_center_y,
+				);
+				if new_alpha > 0.0 {
+					let new_pixel = Rgb(blend(
+						patch.get_pixel(x, y).channels(),
+						&shape.get_color(x as f64, y as f64),
+						new_alpha * shape.alpha,
+						&shape.blending_mode,
+					));
+					patch.put_pixel(x, y, new_pixel);
+				}
+			}
+		}
+
+		Ok(patch)
+	}
+
+	fn get_metadata(&self) -> HashMap<String, String> {
+		get_rng_seed_metadata(self.options.rng_seed)
+	}
+}
@@ -2,15 +2,22 @@ use std::collections::HashMap;
 
 use image::{Pixel, Rgb, RgbImage};
 
+use crate::generator::painter::shape::{Shape, ShapeDetails};
 use crate::generator::painter::Painter;
 use crate::generator::utils::color::BlendingMode;
-use crate::generator::utils::geom::find_target_draw_rect;
+use crate::generator::utils::fill::{get_random_fill, FillType};
+use crate::generator::utils::geom::{
+	find_target_draw_rect, get_edge_coverage, rect_coverage, rect_extents, rotate_point,
+};
+use crate::generator::utils::patch::Patch;
 use crate::generator::utils::pixel::{blend, blend_linear};
+use crate::generator::utils::placement::ErrorMap;
 use crate::generator::utils::random::{
-	get_noise_value, get_random_entry_weighted, get_random_noise_sequence, get_random_range,
-	get_random_ranges_bias_weighted, get_random_size_ranges_bias_weighted, get_rng,
+	get_noise_value, get_random_entry_weighted, get_random_noise_sequence, get_random_position,
+	get_random_ranges_bias_weighted, get_random_ranges_bias_weighted_lazy,
+	get_random_size_ranges_bias_weighted, get_rng,
 };
-use crate::generator::utils::units::{Margins, SizeUnit, WeightedValue};
+use crate::generator::utils::units::{Margins, Rectangle, SizeUnit, WeightedValue};
 use crate::generator::utils::{image::get_pixel_interpolated, random::get_random_color};
 
 pub struct StrokePainter {
@@ -19,6 +26,7 @@ pub struct StrokePainter {
 
 pub struct Options {
 	pub blending_mode: Vec<WeightedValue<BlendingMode>>,
+	pub fill: Vec<WeightedValue<FillType>>,
 	pub alpha: Vec<WeightedValue<(f64, f64)>>,
 	pub alpha_bias: f64,
 	pub width: Vec<WeightedValue<(SizeUnit, SizeUnit)>>,
@@ -29,6 +37,8 @@ pub struct Options {
 	pub wave_height_bias: f64,
 	pub wave_length: Vec<WeightedValue<(SizeUnit, SizeUnit)>>,
 	pub wave_length_bias: f64,
+	pub rotation: Vec<WeightedValue<(f64, f64)>>, // In degrees
+	pub rotation_bias: f64,
 	pub anti_alias: bool,
 	pub color_seed: f64,
 	pub rng_seed: u32,
@@ -42,6 +52,10 @@ impl StrokePainter {
 				value: BlendingMode::default(),
 				weight: 1.0,
 			}],
+			fill: vec![WeightedValue {
+				value: FillType::default(),
+				weight: 1.0,
+			}],
 			alpha: vec![WeightedValue {
 				value: (1.0, 1.0),
 				weight: 1.0,
@@ -67,6 +81,11 @@ impl StrokePainter {
 				weight: 1.0,
 			}],
 			wave_length_bias: 0.0,
+			rotation: vec![WeightedValue {
+				value: (0.0, 0.0),
+				weight: 1.0,
+			}],
+			rotation_bias: 0.0,
 			anti_alias: true,
 			color_seed: 0.0,
 			rng_seed: 0,
@@ -85,7 +104,13 @@ impl StrokePainter {
 }
 
 impl Painter for StrokePainter {
-	fn paint(&self, canvas: &RgbImage, iteration: u32, seed_map: &RgbImage) -> Result<RgbImage, &str> {
+	fn get_shape(
+		&self,
+		canvas: &RgbImage,
+		iteration: u32,
+		seed_map: &RgbImage,
+		error_map: Option<&ErrorMap>,
+	) -> Result<Shape, &str> {
 		let mut rng = get_rng(self.options.rng_seed, iteration);
 
 		let image_area = canvas.dimensions();
@@ -110,17 +135,24 @@ impl Painter for StrokePainter {
 			target_visible_area.1,
 		);
 
-		// Distribute along the axis too
-		let rect_x = get_random_range(
+		// Rotate it, if needed
+		let rotation = get_random_ranges_bias_weighted_lazy(
 			&mut rng,
-			target_area.x as f64,
-			(target_area.x + target_area.width) as f64 - rect_w,
-		);
-		let rect_y = get_random_range(
+			&self.options.rotation,
+			self.options.rotation_bias,
+		)
+		.to_radians();
+		let (extent_x, extent_y) = rect_extents(rect_w, rect_h, rotation);
+
+		// Distribute along the axis too, keeping the whole rotated rect inside the target area
+		let (rect_center_x, rect_center_y) = get_random_position(
 			&mut rng,
-			target_area.y as f64,
-			(target_area.y + target_area.height) as f64 - rect_h,
+			error_map,
+			(target_area.x as f64 + extent_x, (target_area.x + target_area.width) as f64 - extent_x),
+			(target_area.y as f64 + extent_y, (target_area.y + target_area.height) as f64 - extent_y),
 		);
+		let rect_x = rect_center_x - rect_w / 2.0;
+		let rect_y = rect_center_y - rect_h / 2.0;
 
 		// Find final, round positions
 		let x1 = rect_x.round().max(0.0).min(image_area.0 as f64) as u32;
@@ -151,109 +183,203 @@ impl Painter for StrokePainter {
 		// Decide on blending mode
 		let blending_mode = get_random_entry_weighted(&mut rng, &self.options.blending_mode);
 
-		let mut painted_canvas = canvas.clone();
+		// Decide on fill
+		let bounds = Rectangle {
+			x: rect_x + rect_w / 2.0 - extent_x,
+			y: rect_y + rect_h / 2.0 - extent_y,
+			width: extent_x * 2.0,
+			height: extent_y * 2.0,
+		};
+		let fill = get_random_fill(
+			&mut rng,
+			&self.options.fill,
+			&color,
+			&random_color,
+			seed_map,
+			self.options.color_seed,
+			&bounds,
+		);
+
+		// Wavy strokes need the noise of their edges
+		let noise = if wave_height == 0.0 || wave_length == 0.0 {
+			None
+		} else {
+			let margins = wave_height / 2.0;
+			Some(Box::new(get_random_noise_sequence(&mut rng, -margins, margins)))
+		};
+
+		Ok(Shape::new(
+			rect_center_x,
+			rect_center_y,
+			alpha,
+			blending_mode.clone(),
+			fill,
+			target_area,
+			&bounds,
+			ShapeDetails::Strokes {
+				width: rect_w,
+				height: rect_h,
+				rotation,
+				wave_height,
+				wave_length,
+				noise,
+			},
+		))
+	}
+
+	fn paint_shape(&self, canvas: &RgbImage, shape: &Shape) -> Result<Patch, &str> {
+		let (rect_w, rect_h, rotation, wave_height, wave_length, noise) = match &shape.details {
+			ShapeDetails::Strokes {
+				width,
+				height,
+				rotation,
+				wave_height,
+				wave_length,
+				noise,
+			} => (
+				width * shape.scale,
+				height * shape.scale,
+				*rotation,
+				wave_height * shape.scale,
+				wave_length * shape.scale,
+				noise,
+			),
+			_ => return Err("Cannot paint shape from another painter"),
+		};
+		let image_area = canvas.dimensions();
+		let rect_x = shape.x - rect_w / 2.0;
+		let rect_y = shape.y - rect_h / 2.0;
 
 		// Finally, paint
-		if wave_height == 0.0 || wave_length == 0.0 {
-			// Fast path, no waviness
-			for x in x1..x2 {
-				for y in y1..y2 {
-					let new_pixel =
-						Rgb(blend(painted_canvas.get_pixel(x, y).channels(), &color, alpha, &blending_mode));
-					painted_canvas.put_pixel(x, y, new_pixel);
+		let patch = match noise {
+			None if rotation == 0.0 => {
+				// Fast path, no rotation or waviness; find final, round positions
+				let x1 = rect_x.round().max(0.0).min(image_area.0 as f64) as u32;
+				let x2 = (rect_x + rect_w).round().max(0.0).min(image_area.0 as f64) as u32;
+				let y1 = rect_y.round().max(0.0).min(image_area.1 as f64) as u32;
+				let y2 = (rect_y + rect_h).round().max(0.0).min(image_area.1 as f64) as u32;
+				let mut patch = Patch::from_canvas(canvas, x1, y1, x2, y2);
+				for x in x1..x2 {
+					for y in y1..y2 {
+						let new_pixel = Rgb(blend(
+							patch.get_pixel(x, y).channels(),
+							&shape.get_color(x as f64, y as f64),
+							shape.alpha,
+							&shape.blending_mode,
+						));
+						patch.put_pixel(x, y, new_pixel);
+					}
 				}
+				patch
 			}
-		} else {
-			// Slow path, waviness
-			let margins: f64 = wave_height / 2.0;
-			let margin_ceil: u32 = margins.ceil() as u32;
-
-			let noise = get_random_noise_sequence(&mut rng, -margins, margins);
-			let noise_freq = wave_length;
-
-			let x1_safe = (x1 as i64 - margin_ceil as i64).max(0) as u32;
-			let x2_safe = (x2 + margin_ceil).min(image_area.0 as u32);
-			let y1_safe = (y1 as i64 - margin_ceil as i64).max(0) as u32;
-			let y2_safe = (y2 + margin_ceil).min(image_area.1 as u32);
-
-			for x in x1_safe..x2_safe {
-				for y in y1_safe..y2_safe {
-					let alpha_x = if x >= x1 + margin_ceil && x < x2 - margin_ceil {
-						// Inner box
-						1.0
-					} else {
-						// // Part of margin
-						let noise_x = get_noise_value(noise, y as f64 / noise_freq);
-
-						let offset_x1 = x as f64 - (x1 as f64 + noise_x);
-						let alpha_x1 = if offset_x1 > 0.5 {
-							1.0
-						} else if offset_x1 < -0.5 {
-							0.0
-						} else {
-							offset_x1 + 0.5
-						};
+			None => {
+				// Rotated path, no waviness; pixel centers are at round coordinates, so the stroke edges
+				// are half a pixel outside of them. Only the patch is clipped to the image, not the stroke
+				let (edge_x1, edge_y1) = (rect_x - 0.5, rect_y - 0.5);
+				let (edge_x2, edge_y2) = (rect_x + rect_w - 0.5, rect_y + rect_h - 0.5);
+				let center_x = shape.x - 0.5;
+				let center_y = shape.y - 0.5;
+				let (x1_safe, y1_safe, x2_safe, y2_safe) = get_rotated_bounds(
+					edge_x1 - 0.5,
+					edge_y1 - 0.5,
+					edge_x2 + 0.5,
+					edge_y2 + 0.5,
+					rotation,
+					image_area,
+				);
 
-						let offset_x2 = (x2 as f64 + noise_x) - x as f64;
-						let alpha_x2 = if offset_x2 > 0.5 {
-							1.0
-						} else if offset_x2 < -0.5 {
-							0.0
-						} else {
-							offset_x2 + 0.5
-						};
+				let mut patch = Patch::from_canvas(canvas, x1_safe, y1_safe, x2_safe, y2_safe);
+				for x in x1_safe..x2_safe {
+					for y in y1_safe..y2_safe {
+						let (local_x, local_y) =
+							rotate_point(x as f64, y as f64, center_x, center_y, -rotation);
+						let coverage = rect_coverage(local_x, local_y, edge_x1, edge_y1, edge_x2, edge_y2);
+						paint_pixel(
+							&mut patch,
+							x,
+							y,
+							coverage,
+							shape.alpha,
+							&shape.get_color(x as f64, y as f64),
+							&shape.blending_mode,
+							self.options.anti_alias,
+						);
+					}
+				}
+				patch
+			}
+			Some(noise) => {
+				// Slow path, waviness; pixels are tested in the stroke's own (unrotated) coordinate space,
+				// around its unclipped center. Only the patch is clipped to the image. Edges are kept at
+				// round positions, so unrotated strokes look the same as they always have
+				let (x1, y1) = (rect_x.round(), rect_y.round());
+				let (x2, y2) = ((rect_x + rect_w).round(), (rect_y + rect_h).round());
+				let (center_x, center_y) = ((x1 + x2) / 2.0, (y1 + y2) / 2.0);
+
+				let margins: f64 = wave_height / 2.0;
+				let margin_ceil: f64 = margins.ceil();
+
+				let noise_freq = wave_length;
 
-						alpha_x1 * alpha_x2
-					};
+				let (x1_safe, y1_safe, x2_safe, y2_safe) = get_rotated_bounds(
+					x1 - margin_ceil,
+					y1 - margin_ceil,
+					x2 + margin_ceil,
+					y2 + margin_ceil,
+					rotation,
+					image_area,
+				);
 
-					let alpha_y = if y >= y1 + margin_ceil && y < y2 - margin_ceil {
-						// Inner box
-						1.0
-					} else {
-						// // Part of margin
-						let noise_y = get_noise_value(noise, x as f64 / noise_freq);
+				let inner_x1 = x1 + margin_ceil;
+				let inner_x2 = x2 - margin_ceil;
+				let inner_y1 = y1 + margin_ceil;
+				let inner_y2 = y2 - margin_ceil;
 
-						let offset_y1 = y as f64 - (y1 as f64 + noise_y);
-						let alpha_y1 = if offset_y1 > 0.5 {
+				let mut patch = Patch::from_canvas(canvas, x1_safe, y1_safe, x2_safe, y2_safe);
+				for x in x1_safe..x2_safe {
+					for y in y1_safe..y2_safe {
+						let (local_x, local_y) =
+							rotate_point(x as f64, y as f64, center_x, center_y, -rotation);
+
+						let alpha_x = if local_x >= inner_x1 && local_x < inner_x2 {
+							// Inner box
 							1.0
-						} else if offset_y1 < -0.5 {
-							0.0
 						} else {
-							offset_y1 + 0.5
+							// Part of margin
+							let noise_x = get_noise_value(**noise, local_y / noise_freq) * shape.scale;
+							let alpha_x1 = get_edge_coverage(local_x - (x1 + noise_x));
+							let alpha_x2 = get_edge_coverage((x2 + noise_x) - local_x);
+							alpha_x1 * alpha_x2
 						};
 
-						let offset_y2 = (y2 as f64 + noise_y) - y as f64;
-						let alpha_y2 = if offset_y2 > 0.5 {
+						let alpha_y = if local_y >= inner_y1 && local_y < inner_y2 {
+							// Inner box
 							1.0
-						} else if offset_y2 < -0.5 {
-							0.0
 						} else {
-							offset_y2 + 0.5
+							// Part of margin
+							let noise_y = get_noise_value(**noise, local_x / noise_freq) * shape.scale;
+							let alpha_y1 = get_edge_coverage(local_y - (y1 + noise_y));
+							let alpha_y2 = get_edge_coverage((y2 + noise_y) - local_y);
+							alpha_y1 * alpha_y2
 						};
 
-						alpha_y1 * alpha_y2
-					};
-
-					let new_pixel = Rgb(blend(
-						painted_canvas.get_pixel(x, y).channels(),
-						&color,
-						if self.options.anti_alias {
-							alpha_x * alpha_y * alpha
-						} else {
-							if alpha_x * alpha_y >= 0.5 {
-								1.0
-							} else {
-								0.0
-							}
-						},
-						&blending_mode,
-					));
-					painted_canvas.put_pixel(x, y, new_pixel);
+						paint_pixel(
+							&mut patch,
+							x,
+							y,
+							alpha_x * alpha_y,
+							shape.alpha,
+							&shape.get_color(x as f64, y as f64),
+							&shape.blending_mode,
+							self.options.anti_alias,
+						);
+					}
 				}
+				patch
 			}
-		}
+		};
 
-		Ok(painted_canvas)
+		Ok(patch)
 	}
 
 	fn get_metadata(&self) -> HashMap<String, String> {
@@ -262,3 +388,46 @@ impl Painter for StrokePainter {
 		data
 	}
 }
+
+/// Finds the pixel bounds, clipped to the image, of a rect rotated around its center
+fn get_rotated_bounds(
+	x1: f64,
+	y1: f64,
+	x2: f64,
+	y2: f64,
+	rotation: f64,
+	image_area: (u32, u32),
+) -> (u32, u32, u32, u32) {
+	let (extent_x, extent_y) = rect_extents(x2 - x1, y2 - y1, rotation);
+	let center_x = (x1 + x2) / 2.0;
+	let center_y = (y1 + y2) / 2.0;
+	(
+		(center_x - extent_x).floor().max(0.0).min(image_area.0 as f64) as u32,
+		(center_y - extent_y).floor().max(0.0).min(image_area.1 as f64) as u32,
+		(center_x + extent_x).ceil().max(0.0).min(image_area.0 as f64) as u32,
+		(center_y + extent_y).ceil().max(0.0).min(image_area.1 as f64) as u32,
+	)
+}
+
+#[inline(always)]
+#[allow(clippy::too_many_arguments)]
+fn paint_pixel(
+	patch: &mut Patch,
+	x: u32,
+	y: u32,
+	coverage: f64,
+	alpha: f64,
+	color: &[u8; 3],
+	blending_mode: &BlendingMode,
+	anti_alias: bool,
+) {
+	let new_alpha = if anti_alias {
+		coverage * alpha
+	} else if coverage >= 0.5 {
+		alpha
+	} else {
+		0.0
+	};
+	let new_pixel = Rgb(blend(patch.get_pixel(x, y).channels(), color, new_alpha, blending_mode));
+	patch.put_pixel(x, y, new_pixel);
+}
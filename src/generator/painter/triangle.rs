@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+
+use image::{Pixel, Rgb, RgbImage};
+
+use crate::generator::painter::shape::{Shape, ShapeDetails};
+use crate::generator::painter::{
+	get_random_shape, get_rng_seed_metadata, Painter, ShapeGeometry, ShapeOptions,
+};
+use crate::generator::utils::color::BlendingMode;
+use crate::generator::utils::fill::FillType;
+use crate::generator::utils::geom::{get_edge_alpha, triangle_inner_distance};
+use crate::generator::utils::patch::Patch;
+use crate::generator::utils::pixel::blend;
+use crate::generator::utils::placement::ErrorMap;
+use crate::generator::utils::random::{
+	get_random_position, get_random_range, get_random_size_ranges_bias_weighted,
+};
+use crate::generator::utils::units::{Margins, Rectangle, SizeUnit, WeightedValue};
+
+pub struct TrianglePainter {
+	pub options: Options,
+}
+
+pub struct Options {
+	pub blending_mode: Vec<WeightedValue<BlendingMode>>,
+	pub fill: Vec<WeightedValue<FillType>>,
+	pub alpha: Vec<WeightedValue<(f64, f64)>>,
+	pub alpha_bias: f64,
+	pub width: Vec<WeightedValue<(SizeUnit, SizeUnit)>>,
+	pub height: Vec<WeightedValue<(SizeUnit, SizeUnit)>>,
+	pub width_bias: f64, // 0 = normal; -1 = quad bias towards small, 1 = quad bias towards big, etc
+	pub height_bias: f64, // 0 = normal; -1 = quad bias towards small, 1 = quad bias towards big, etc
+	pub anti_alias: bool,
+	pub color_seed: f64,
+	pub rng_seed: u32,
+	pub margins: Margins<SizeUnit>,
+}
+
+impl TrianglePainter {
+	pub fn new() -> Self {
+		let options = Options {
+			blending_mode: vec![WeightedValue {
+				value: BlendingMode::default(),
+				weight: 1.0,
+			}],
+			fill: vec![WeightedValue {
+				value: FillType::default(),
+				weight: 1.0,
+			}],
+			alpha: vec![WeightedValue {
+				value: (1.0, 1.0),
+				weight: 1.0,
+			}],
+			alpha_bias: 0.0,
+			width: vec![WeightedValue {
+				value: (SizeUnit::Fraction(0.0), SizeUnit::Fraction(1.0)),
+				weight: 1.0,
+			}],
+			width_bias: 0.0,
+			height: vec![WeightedValue {
+				value: (SizeUnit::Fraction(0.0), SizeUnit::Fraction(1.0)),
+				weight: 1.0,
+			}],
+			height_bias: 0.0,
+			anti_alias: true,
+			color_seed: 0.0,
+			rng_seed: 0,
+			margins: Margins::<SizeUnit> {
+				top: SizeUnit::Pixels(0),
+				right: SizeUnit::Pixels(0),
+				bottom: SizeUnit::Pixels(0),
+				left: SizeUnit::Pixels(0),
+			},
+		};
+
+		TrianglePainter {
+			options,
+		}
+	}
+}
+
+impl Painter for TrianglePainter {
+	fn get_shape(
+		&self,
+		canvas: &RgbImage,
+		iteration: u32,
+		seed_map: &RgbImage,
+		error_map: Option<&ErrorMap>,
+	) -> Result<Shape, &str> {
+		let options = ShapeOptions {
+			blending_mode: &self.options.blending_mode,
+			fill: &self.options.fill,
+			alpha: &self.options.alpha,
+			alpha_bias: self.options.alpha_bias,
+			color_seed: self.options.color_seed,
+			rng_seed: self.options.rng_seed,
+			margins: &self.options.margins,
+		};
+		get_random_shape(&options, canvas, iteration, seed_map, |rng, target_area, target_visible_area| {
+			// Find random dimensions for the box containing the triangle
+			let box_w = get_random_size_ranges_bias_weighted(
+				rng,
+				&self.options.width,
+				self.options.width_bias,
+				target_visible_area.0,
+			);
+			let box_h = get_random_size_ranges_bias_weighted(
+				rng,
+				&self.options.height,
+				self.options.height_bias,
+				target_visible_area.1,
+			);
+
+			// Distribute the box along the axis too
+			let (box_center_x, box_center_y) = get_random_position(
+				rng,
+				error_map,
+				(
+					target_area.x as f64 + box_w / 2.0,
+					(target_area.x + target_area.width) as f64 - box_w / 2.0,
+				),
+				(
+					target_area.y as f64 + box_h / 2.0,
+					(target_area.y + target_area.height) as f64 - box_h / 2.0,
+				),
+			);
+			let box_x = box_center_x - box_w / 2.0;
+			let box_y = box_center_y - box_h / 2.0;
+
+			// Pick the vertices anywhere inside the box
+			let mut vertices = [(0.0f64, 0.0f64); 3];
+			for vertex in vertices.iter_mut() {
+				vertex.0 = get_random_range(rng, box_x, box_x + box_w);
+				vertex.1 = get_random_range(rng, box_y, box_y + box_h);
+			}
+			let (p1, p2, p3) = (vertices[0], vertices[1], vertices[2]);
+			let (min_x, min_y, max_x, max_y) = get_triangle_bounds(p1, p2, p3);
+
+			ShapeGeometry {
+				x: box_center_x,
+				y: box_center_y,
+				seed_point: ((p1.0 + p2.0 + p3.0) / 3.0, (p1.1 + p2.1 + p3.1) / 3.0),
+				bounds: Rectangle {
+					x: min_x,
+					y: min_y,
+					width: max_x - min_x,
+					height: max_y - min_y,
+				},
+				details: ShapeDetails::Triangles {
+					vertices,
+				},
+			}
+		})
+	}
+
+	fn paint_shape(&self, canvas: &RgbImage, shape: &Shape) -> Result<Patch, &str> {
+		let vertices = match shape.details {
+			ShapeDetails::Triangles {
+				vertices,
+			} => vertices.map(|vertex| shape.transform_point(vertex)),
+			_ => return Err("Cannot paint shape from another painter"),
+		};
+		let (p1, p2, p3) = (vertices[0], vertices[1], vertices[2]);
+		let image_area = canvas.dimensions();
+
+		// Find final, round positions
+		let (min_x, min_y, max_x, max_y) = get_triangle_bounds(p1, p2, p3);
+		let x1 = min_x.floor().max(0.0).min(image_area.0 as f64) as u32;
+		let y1 = min_y.floor().max(0.0).min(image_area.1 as f64) as u32;
+		let x2 = max_x.ceil().max(0.0).min(image_area.0 as f64) as u32;
+		let y2 = max_y.ceil().max(0.0).min(image_area.1 as f64) as u32;
+
+		// Finally, paint
+		let mut patch = Patch::from_canvas(canvas, x1, y1, x2, y2);
+		for x in x1..x2 {
+			for y in y1..y2 {
+				let dist = triangle_inner_distance(x as f64, y as f64, p1, p2, p3);
+				if dist > 0.0 {
+					let new_alpha = get_edge_alpha(dist, self.options.anti_alias);
+					let new_pixel = Rgb(blend(
+						patch.get_pixel(x, y).channels(),
+						&shape.get_color(x as f64, y as f64),
+						new_alpha * shape.alpha,
+						&shape.blending_mode,
+					));
+					patch.put_pixel(x, y, new_pixel);
+				}
+			}
+		}
+
+		Ok(patch)
+	}
+
+	fn get_metadata(&self) -> HashMap<String, String> {
+		get_rng_seed_metadata(self.options.rng_seed)
+	}
+}
+
+/// Finds the bounds of a triangle, as (min x, min y, max x, max y)
+fn get_triangle_bounds(p1: (f64, f64), p2: (f64, f64), p3: (f64, f64)) -> (f64, f64, f64, f64) {
+	(p1.0.min(p2.0).min(p3.0), p1.1.min(p2.1).min(p3.1), p1.0.max(p2.0).max(p3.0), p1.1.max(p2.1).max(p3.1))
+}
@@ -0,0 +1,115 @@
+use strum_macros::{Display, EnumString};
+
+use crate::generator::utils::random::rng::Rng;
+
+/// How the generator decides whether to accept a candidate.
+#[derive(Clone, Copy, Debug, Default, Display, EnumString, PartialEq)]
+pub enum Acceptance {
+	#[default]
+	#[strum(serialize = "greedy")]
+	Greedy,
+	#[strum(serialize = "annealing")]
+	Annealing,
+}
+
+/// Decides whether candidates are accepted. Greedy acceptance only takes candidates that are better than
+/// the current image; simulated annealing also takes worse candidates, with a chance that decreases as
+/// they get worse and as the temperature cools down after each try.
+#[derive(Clone, Debug)]
+pub struct Acceptor {
+	acceptance: Acceptance,
+	temperature: f64,
+	cooling_rate: f64,
+}
+
+impl Acceptor {
+	/// Creates an acceptor. The temperature is in the same unit as differences, and is multiplied by the
+	/// cooling rate after each try.
+	pub fn new(acceptance: Acceptance, temperature: f64, cooling_rate: f64) -> Acceptor {
+		Acceptor {
+			acceptance,
+			temperature: match acceptance {
+				Acceptance::Greedy => 0.0,
+				Acceptance::Annealing => temperature.max(0.0),
+			},
+			cooling_rate: cooling_rate.clamp(0.0, 1.0),
+		}
+	}
+
+	pub fn acceptance(&self) -> Acceptance {
+		self.acceptance
+	}
+
+	pub fn temperature(&self) -> f64 {
+		self.temperature
+	}
+
+	/// Sets the current temperature, e.g. to resume a process that has already cooled down.
+	pub fn set_temperature(&mut self, temperature: f64) {
+		if self.acceptance == Acceptance::Annealing {
+			self.temperature = temperature.max(0.0);
+		}
+	}
+
+	/// Whether candidates with a given difference could ever be accepted, so worse candidates can be
+	/// discarded early.
+	pub fn is_candidate(&self, curr_diff: f64, new_diff: f64) -> bool {
+		new_diff < curr_diff || self.temperature > 0.0
+	}
+
+	/// Decides whether a candidate is accepted. Candidates that don't change the difference are never
+	/// accepted.
+	pub fn accepts(&self, curr_diff: f64, new_diff: f64, rng: &mut Rng) -> bool {
+		if new_diff < curr_diff {
+			true
+		} else if new_diff > curr_diff && self.temperature > 0.0 {
+			rng.next_f64() < (-(new_diff - curr_diff) / self.temperature).exp()
+		} else {
+			false
+		}
+	}
+
+	/// Cools the temperature down, after a try.
+	pub fn cool(&mut self) {
+		self.temperature *= self.cooling_rate;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_acceptor_greedy() {
+		let mut rng = Rng::from_seed(1);
+		let acceptor = Acceptor::new(Acceptance::Greedy, 0.1, 0.5);
+		assert_eq!(acceptor.temperature(), 0.0);
+		assert!(acceptor.is_candidate(0.5, 0.4));
+		assert!(!acceptor.is_candidate(0.5, 0.6));
+		assert!(acceptor.accepts(0.5, 0.4, &mut rng));
+		assert!(!acceptor.accepts(0.5, 0.5, &mut rng));
+		assert!(!acceptor.accepts(0.5, 0.5001, &mut rng));
+	}
+
+	#[test]
+	fn test_acceptor_annealing() {
+		let mut rng = Rng::from_seed(1);
+		let mut acceptor = Acceptor::new(Acceptance::Annealing, 0.01, 0.5);
+		assert_eq!(acceptor.temperature(), 0.01);
+		assert!(acceptor.is_candidate(0.5, 0.6));
+		assert!(acceptor.accepts(0.5, 0.4, &mut rng));
+		assert!(!acceptor.accepts(0.5, 0.5, &mut rng));
+
+		// Slightly worse candidates are accepted most of the time, much worse ones almost never
+		let num_slightly_worse = (0..1000).filter(|_| acceptor.accepts(0.5, 0.501, &mut rng)).count();
+		let num_much_worse = (0..1000).filter(|_| acceptor.accepts(0.5, 0.6, &mut rng)).count();
+		assert!((850..950).contains(&num_slightly_worse));
+		assert_eq!(num_much_worse, 0);
+
+		// Cooling down
+		acceptor.cool();
+		assert_eq!(acceptor.temperature(), 0.005);
+		acceptor.cool();
+		assert_eq!(acceptor.temperature(), 0.0025);
+	}
+}
@@ -0,0 +1,145 @@
+use image::{GrayImage, RgbImage};
+use strum_macros::{Display, EnumString};
+
+use crate::generator::utils::patch::Patch;
+
+/// How the colors of new elements are decided.
+#[derive(Clone, Copy, Debug, Default, Display, EnumString, PartialEq)]
+pub enum ColorMode {
+	#[default]
+	#[strum(serialize = "random")]
+	Random,
+	#[strum(serialize = "seed")]
+	Seed,
+	#[strum(serialize = "optimal")]
+	Optimal,
+}
+
+/// Finds the flat color that, painted with normal blending over the canvas, brings the pixels of an element
+/// closest to the target. `coverage` has how much of each pixel the element covers, from black (not
+/// covered) to white (fully covered). Pixels are only covered as much as the paint mask allows, and are
+/// weighted by the importance map, the same way they are when diffing. Returns `None` if the element
+/// doesn't cover any pixel.
+pub fn get_optimal_color(
+	coverage: &Patch,
+	current: &RgbImage,
+	target: &RgbImage,
+	alpha: f64,
+	paint_mask: Option<&GrayImage>,
+	importance_map: Option<&GrayImage>,
+) -> Option<[u8; 3]> {
+	// Each painted pixel becomes `current * (1 - weight) + color * weight`, so the weighted least squares
+	// solution is the sum of `importance * weight * (target - current * (1 - weight))` over the sum of
+	// `importance * weight²`
+	let mut sums = [0.0f64; 3];
+	let mut sum_weights = 0.0f64;
+	for (x, y, pixel) in coverage.image.enumerate_pixels() {
+		let (canvas_x, canvas_y) = (coverage.x + x, coverage.y + y);
+		let mask = paint_mask.map_or(1.0, |mask| mask.get_pixel(canvas_x, canvas_y)[0] as f64 / 255.0);
+		let weight = pixel[0] as f64 / 255.0 * alpha * mask;
+		let importance =
+			importance_map.map_or(1.0, |map| map.get_pixel(canvas_x, canvas_y)[0] as f64 / 255.0);
+		if weight > 0.0 && importance > 0.0 {
+			let current_pixel = current.get_pixel(canvas_x, canvas_y);
+			let target_pixel = target.get_pixel(canvas_x, canvas_y);
+			for channel in 0..3 {
+				let remaining = target_pixel[channel] as f64 - current_pixel[channel] as f64 * (1.0 - weight);
+				sums[channel] += importance * weight * remaining;
+			}
+			sum_weights += importance * weight * weight;
+		}
+	}
+
+	if sum_weights > 0.0 {
+		Some(sums.map(|sum| (sum / sum_weights).round().clamp(0.0, 255.0) as u8))
+	} else {
+		None
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use image::{Luma, Rgb, RgbImage};
+
+	use super::*;
+
+	#[test]
+	fn test_get_optimal_color() {
+		let current = RgbImage::from_pixel(4, 4, Rgb([0u8, 100u8, 200u8]));
+		let target = RgbImage::from_fn(4, 4, |x, _y| {
+			if x < 2 {
+				Rgb([50u8, 100u8, 150u8])
+			} else {
+				Rgb([250u8, 0u8, 0u8])
+			}
+		});
+
+		// Fully covering the left half
+		let mut coverage = Patch::from_canvas(&RgbImage::new(4, 4), 0, 0, 3, 4);
+		for y in 0..4 {
+			coverage.put_pixel(0, y, Rgb([255u8, 255u8, 255u8]));
+			coverage.put_pixel(1, y, Rgb([255u8, 255u8, 255u8]));
+		}
+		assert_eq!(get_optimal_color(&coverage, &current, &target, 1.0, None, None), Some([50, 100, 150]));
+
+		// Half transparent, so the color needs to make up for the current pixels
+		assert_eq!(get_optimal_color(&coverage, &current, &target, 0.5, None, None), Some([100, 100, 100]));
+
+		// Out of range colors are clamped
+		assert_eq!(get_optimal_color(&coverage, &current, &target, 0.2, None, None), Some([250, 100, 0]));
+
+		// Nothing covered
+		let coverage = Patch::from_canvas(&RgbImage::new(4, 4), 0, 0, 3, 4);
+		assert_eq!(get_optimal_color(&coverage, &current, &target, 1.0, None, None), None);
+	}
+
+	#[test]
+	fn test_get_optimal_color_with_maps() {
+		let current = RgbImage::from_pixel(4, 4, Rgb([0u8, 0u8, 0u8]));
+		let target = RgbImage::from_fn(4, 4, |x, _y| {
+			if x < 2 {
+				Rgb([200u8, 100u8, 0u8])
+			} else {
+				Rgb([0u8, 100u8, 200u8])
+			}
+		});
+
+		// Covering the whole image, but the right half is masked out
+		let coverage = Patch {
+			x: 0,
+			y: 0,
+			image: RgbImage::from_pixel(4, 4, Rgb([255u8, 255u8, 255u8])),
+		};
+		assert_eq!(get_optimal_color(&coverage, &current, &target, 1.0, None, None), Some([100, 100, 100]));
+		let paint_mask = GrayImage::from_fn(4, 4, |x, _y| {
+			Luma([if x < 2 {
+				255u8
+			} else {
+				0u8
+			}])
+		});
+		assert_eq!(
+			get_optimal_color(&coverage, &current, &target, 1.0, Some(&paint_mask), None),
+			Some([200, 100, 0])
+		);
+
+		// Only the right half matters
+		let importance_map = GrayImage::from_fn(4, 4, |x, _y| {
+			Luma([if x < 2 {
+				0u8
+			} else {
+				255u8
+			}])
+		});
+		assert_eq!(
+			get_optimal_color(&coverage, &current, &target, 1.0, None, Some(&importance_map)),
+			Some([0, 100, 200])
+		);
+
+		// Nothing that can be painted matters
+		assert_eq!(
+			get_optimal_color(&coverage, &current, &target, 1.0, Some(&paint_mask), Some(&importance_map)),
+			None
+		);
+	}
+}
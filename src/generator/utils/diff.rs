@@ -0,0 +1,603 @@
+use image::{GrayImage, RgbImage};
+use strum_macros::{Display, EnumString};
+
+use crate::generator::utils::image::{diff_pixel, get_luma};
+use crate::generator::utils::patch::Patch;
+use crate::generator::utils::pixel::{delta_e_2000, delta_e_76, delta_e_94, to_lab};
+use crate::generator::utils::units::Rectangle;
+
+// Delta-E values are scaled so black and white are 100% different, like in the luma metric
+const DELTA_E_SCALE: f64 = 1.0 / 100.0;
+
+const DIFF_MAP_BAND_HEIGHT: u32 = 64;
+
+// SSIM compares the neighborhood of each pixel, in a square window with this radius
+const SSIM_RADIUS: u32 = 3;
+const SSIM_C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+const SSIM_C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+
+/// How the difference between two pixels is measured.
+#[derive(Clone, Copy, Debug, Default, Display, EnumString, PartialEq)]
+pub enum DiffMetric {
+	#[default]
+	#[strum(serialize = "luma")]
+	Luma,
+	#[strum(serialize = "lab-de76")]
+	LabDe76,
+	#[strum(serialize = "lab-de94")]
+	LabDe94,
+	#[strum(serialize = "lab-de2000")]
+	LabDe2000,
+	#[strum(serialize = "ssim")]
+	Ssim,
+}
+
+impl DiffMetric {
+	fn is_lab(&self) -> bool {
+		matches!(self, DiffMetric::LabDe76 | DiffMetric::LabDe94 | DiffMetric::LabDe2000)
+	}
+}
+
+/// Keeps the difference of each pixel of the current image from the target, so the difference of a
+/// candidate patch can be found by only looking at the pixels inside of it. Pixels can be weighted by an
+/// importance map, in which case the difference is their weighted average.
+#[derive(Clone, Debug)]
+pub struct DiffMap {
+	width: u32,
+	height: u32,
+	metric: DiffMetric,
+	errors: Vec<f32>,
+	target_lab: Vec<[f32; 3]>,
+	current_luma: Vec<u8>,
+	target_luma: Vec<u8>,
+	weights: Vec<f32>,
+	weight_sum: f64,
+	total: f64,
+	labels: Vec<u16>,
+	label_errors: Vec<f64>,
+}
+
+impl DiffMap {
+	pub fn new(
+		current: &RgbImage,
+		target: &RgbImage,
+		metric: DiffMetric,
+		importance_map: Option<&GrayImage>,
+	) -> DiffMap {
+		let (width, height) = current.dimensions();
+		let target_samples = target.as_flat_samples().samples;
+
+		// CIELAB conversion is costly, so target colors are only converted once
+		let target_lab = if metric.is_lab() {
+			target_samples.chunks_exact(3).map(to_lab_f32).collect()
+		} else {
+			vec![]
+		};
+
+		// SSIM needs the brightness of the pixels around each pixel too
+		let (current_luma, target_luma) = if metric == DiffMetric::Ssim {
+			(get_luma_values(current), get_luma_values(target))
+		} else {
+			(vec![], vec![])
+		};
+
+		// Weights of each pixel, from black (doesn't matter) to white (full weight)
+		let (weights, weight_sum) = match importance_map {
+			Some(map) => {
+				let weights: Vec<f32> = map.as_raw().iter().map(|&value| value as f32 / 255.0).collect();
+				let weight_sum = weights.iter().map(|&weight| weight as f64).sum();
+				(weights, weight_sum)
+			}
+			None => (vec![], width as f64 * height as f64),
+		};
+
+		let mut diff_map = DiffMap {
+			width,
+			height,
+			metric,
+			errors: vec![0.0; (width * height) as usize],
+			target_lab,
+			current_luma,
+			target_luma,
+			weights,
+			weight_sum,
+			total: 0.0,
+			labels: vec![],
+			label_errors: vec![],
+		};
+
+		// Differences are found by applying the current image over itself, in bands to limit memory use
+		for y in (0..height).step_by(DIFF_MAP_BAND_HEIGHT as usize) {
+			diff_map.apply(&Patch::from_canvas(current, 0, y, width, y + DIFF_MAP_BAND_HEIGHT), target);
+		}
+		diff_map
+	}
+
+	/// The current difference from the target, from `0.0` (identical) to `1.0` (e.g. white vs black).
+	pub fn diff(&self) -> f64 {
+		self.total / self.num_pixels()
+	}
+
+	/// Sets the region label of each pixel, so the sum of differences of each region can be tracked.
+	pub fn set_labels(&mut self, labels: &[u16], num_labels: usize) {
+		self.labels = labels.to_vec();
+		self.label_errors = vec![0.0; num_labels];
+		for (index, &label) in labels.iter().enumerate() {
+			self.label_errors[label as usize] += self.errors[index] as f64;
+		}
+	}
+
+	/// The sum of the differences of all pixels with a region label.
+	pub fn get_label_error(&self, label: usize) -> f64 {
+		self.label_errors[label]
+	}
+
+	/// The running sums of differences, of all pixels and of each region. They're kept as patches are
+	/// applied, so they can drift slightly from the sums of the differences of each pixel.
+	pub fn get_sums(&self) -> (f64, &[f64]) {
+		(self.total, &self.label_errors)
+	}
+
+	/// Restores previously kept running sums of differences, so a process can be resumed exactly.
+	pub fn set_sums(&mut self, total: f64, label_errors: &[f64]) {
+		self.total = total;
+		if label_errors.len() == self.label_errors.len() {
+			self.label_errors = label_errors.to_vec();
+		}
+	}
+
+	/// Finds what the difference from the target would be if a patch was committed to the current image.
+	pub fn get_patch_diff(&self, patch: &Patch, target: &RgbImage) -> f64 {
+		(self.total + self.get_patch_change(patch, target)) / self.num_pixels()
+	}
+
+	/// The difference of each pixel from the target, row by row.
+	pub fn errors(&self) -> &[f32] {
+		&self.errors
+	}
+
+	/// Updates the differences after a patch has been committed to the current image, returning the area
+	/// of pixels whose differences changed.
+	pub fn apply(&mut self, patch: &Patch, target: &RgbImage) -> Rectangle<u32> {
+		if self.metric == DiffMetric::Ssim {
+			let (area, errors) = self.get_ssim_errors(patch);
+			self.total += self.get_area_change(&area, &errors);
+			for y in 0..area.height {
+				for x in 0..area.width {
+					let index = self.get_index(area.x + x, area.y + y);
+					self.set_error(index, errors[(y * area.width + x) as usize]);
+				}
+			}
+			let (width, height) = patch.dimensions();
+			for y in 0..height {
+				for x in 0..width {
+					let index = self.get_index(patch.x + x, patch.y + y);
+					self.current_luma[index] = get_luma_value(&patch.image.get_pixel(x, y).0);
+				}
+			}
+			area
+		} else {
+			self.total += self.get_patch_change(patch, target);
+			let (width, height) = patch.dimensions();
+			for y in 0..height {
+				for x in 0..width {
+					let index = self.get_index(patch.x + x, patch.y + y);
+					self.set_error(index, self.get_patch_pixel_error(patch, target, x, y));
+				}
+			}
+			Rectangle {
+				x: patch.x,
+				y: patch.y,
+				width,
+				height,
+			}
+		}
+	}
+
+	/// Finds how much the sum of differences changes with a patch. Unchanged pixels always cancel each
+	/// other out, so a patch that doesn't change anything never looks like an improvement.
+	fn get_patch_change(&self, patch: &Patch, target: &RgbImage) -> f64 {
+		if self.metric == DiffMetric::Ssim {
+			let (area, errors) = self.get_ssim_errors(patch);
+			return self.get_area_change(&area, &errors);
+		}
+
+		let (width, height) = patch.dimensions();
+		let mut change = 0.0;
+		for y in 0..height {
+			for x in 0..width {
+				let index = self.get_index(patch.x + x, patch.y + y);
+				change += self.get_patch_pixel_error(patch, target, x, y) as f64 - self.errors[index] as f64;
+			}
+		}
+		change
+	}
+
+	fn get_area_change(&self, area: &Rectangle<u32>, errors: &[f32]) -> f64 {
+		let mut change = 0.0;
+		for y in 0..area.height {
+			for x in 0..area.width {
+				let index = self.get_index(area.x + x, area.y + y);
+				change += errors[(y * area.width + x) as usize] as f64 - self.errors[index] as f64;
+			}
+		}
+		change
+	}
+
+	/// Finds the SSIM errors (`1 - SSIM`) of all pixels whose neighborhood changes with a patch, returning
+	/// them along with their area. Sums are calculated with integers, so they are exact no matter the area.
+	fn get_ssim_errors(&self, patch: &Patch) -> (Rectangle<u32>, Vec<f32>) {
+		let (patch_width, patch_height) = patch.dimensions();
+
+		// Pixels whose neighborhood changes
+		let x1 = patch.x.saturating_sub(SSIM_RADIUS);
+		let y1 = patch.y.saturating_sub(SSIM_RADIUS);
+		let x2 = (patch.x + patch_width + SSIM_RADIUS).min(self.width);
+		let y2 = (patch.y + patch_height + SSIM_RADIUS).min(self.height);
+
+		// Pixels in those neighborhoods
+		let data_x1 = x1.saturating_sub(SSIM_RADIUS);
+		let data_y1 = y1.saturating_sub(SSIM_RADIUS);
+		let data_x2 = (x2 + SSIM_RADIUS).min(self.width);
+		let data_y2 = (y2 + SSIM_RADIUS).min(self.height);
+		let data_width = (data_x2 - data_x1) as usize;
+		let data_height = (data_y2 - data_y1) as usize;
+
+		// Summed-area tables of a, b, a², b², and a*b, with an extra leading row and column of zeros
+		let stride = data_width + 1;
+		let mut sums = vec![[0i64; 5]; stride * (data_height + 1)];
+		for y in 0..data_height {
+			for x in 0..data_width {
+				let (canvas_x, canvas_y) = (data_x1 + x as u32, data_y1 + y as u32);
+				let index = self.get_index(canvas_x, canvas_y);
+				let a = if canvas_x >= patch.x
+					&& canvas_x < patch.x + patch_width
+					&& canvas_y >= patch.y
+					&& canvas_y < patch.y + patch_height
+				{
+					get_luma_value(&patch.get_pixel(canvas_x, canvas_y).0)
+				} else {
+					self.current_luma[index]
+				} as i64;
+				let b = self.target_luma[index] as i64;
+				let values = [a, b, a * a, b * b, a * b];
+				let i = (y + 1) * stride + x + 1;
+				for (k, value) in values.iter().enumerate() {
+					sums[i][k] = value + sums[i - 1][k] + sums[i - stride][k] - sums[i - stride - 1][k];
+				}
+			}
+		}
+
+		let mut errors = Vec::with_capacity(((x2 - x1) * (y2 - y1)) as usize);
+		for y in y1..y2 {
+			for x in x1..x2 {
+				let wx1 = (x.saturating_sub(SSIM_RADIUS) - data_x1) as usize;
+				let wy1 = (y.saturating_sub(SSIM_RADIUS) - data_y1) as usize;
+				let wx2 = ((x + SSIM_RADIUS + 1).min(self.width) - data_x1) as usize;
+				let wy2 = ((y + SSIM_RADIUS + 1).min(self.height) - data_y1) as usize;
+				let window = |k: usize| -> f64 {
+					(sums[wy2 * stride + wx2][k] - sums[wy1 * stride + wx2][k] - sums[wy2 * stride + wx1][k]
+						+ sums[wy1 * stride + wx1][k]) as f64
+				};
+				let n = ((wx2 - wx1) * (wy2 - wy1)) as f64;
+				let mean_a = window(0) / n;
+				let mean_b = window(1) / n;
+				let variance_a = window(2) / n - mean_a * mean_a;
+				let variance_b = window(3) / n - mean_b * mean_b;
+				let covariance = window(4) / n - mean_a * mean_b;
+				let ssim = ((2.0 * mean_a * mean_b + SSIM_C1) * (2.0 * covariance + SSIM_C2))
+					/ ((mean_a * mean_a + mean_b * mean_b + SSIM_C1) * (variance_a + variance_b + SSIM_C2));
+				errors.push((1.0 - ssim) as f32 * self.get_weight(self.get_index(x, y)));
+			}
+		}
+
+		(
+			Rectangle {
+				x: x1,
+				y: y1,
+				width: x2 - x1,
+				height: y2 - y1,
+			},
+			errors,
+		)
+	}
+
+	#[inline(always)]
+	fn set_error(&mut self, index: usize, error: f32) {
+		if !self.labels.is_empty() {
+			self.label_errors[self.labels[index] as usize] += error as f64 - self.errors[index] as f64;
+		}
+		self.errors[index] = error;
+	}
+
+	#[inline(always)]
+	fn get_patch_pixel_error(&self, patch: &Patch, target: &RgbImage, x: u32, y: u32) -> f32 {
+		let (canvas_x, canvas_y) = (patch.x + x, patch.y + y);
+		let index = self.get_index(canvas_x, canvas_y);
+		self.get_pixel_error(&patch.image.get_pixel(x, y).0, &target.get_pixel(canvas_x, canvas_y).0, index)
+			* self.get_weight(index)
+	}
+
+	#[inline(always)]
+	fn get_weight(&self, index: usize) -> f32 {
+		if self.weights.is_empty() {
+			1.0
+		} else {
+			self.weights[index]
+		}
+	}
+
+	#[inline(always)]
+	fn get_pixel_error(&self, pixel: &[u8], target_pixel: &[u8], index: usize) -> f32 {
+		match self.metric {
+			DiffMetric::Luma => diff_pixel(pixel, target_pixel) as f32,
+			DiffMetric::LabDe76 => self.get_lab_pixel_error(pixel, index, delta_e_76),
+			DiffMetric::LabDe94 => self.get_lab_pixel_error(pixel, index, delta_e_94),
+			DiffMetric::LabDe2000 => self.get_lab_pixel_error(pixel, index, delta_e_2000),
+			DiffMetric::Ssim => unreachable!("SSIM errors depend on the pixels around each pixel"),
+		}
+	}
+
+	#[inline(always)]
+	fn get_lab_pixel_error(
+		&self,
+		pixel: &[u8],
+		index: usize,
+		delta_e: fn(&[f64; 3], &[f64; 3]) -> f64,
+	) -> f32 {
+		let target_lab = self.target_lab[index];
+		let lab = to_lab_f32(pixel);
+		let target_lab = [target_lab[0] as f64, target_lab[1] as f64, target_lab[2] as f64];
+		let lab = [lab[0] as f64, lab[1] as f64, lab[2] as f64];
+		(delta_e(&target_lab, &lab) * DELTA_E_SCALE) as f32
+	}
+
+	#[inline(always)]
+	fn get_index(&self, x: u32, y: u32) -> usize {
+		(y * self.width + x) as usize
+	}
+
+	/// The number of pixels the total is averaged over; with an importance map, pixels only count as much
+	/// as their weight.
+	fn num_pixels(&self) -> f64 {
+		self.weight_sum.max(f64::MIN_POSITIVE)
+	}
+}
+
+#[inline(always)]
+fn get_luma_value(pixel: &[u8]) -> u8 {
+	(get_luma(pixel) * 255.0).round() as u8
+}
+
+fn get_luma_values(image: &RgbImage) -> Vec<u8> {
+	image.as_flat_samples().samples.chunks_exact(3).map(get_luma_value).collect()
+}
+
+/// Converts a pixel to CIELAB with the same precision used for the target colors, so identical pixels
+/// always have no difference.
+#[inline(always)]
+fn to_lab_f32(pixel: &[u8]) -> [f32; 3] {
+	let lab = to_lab(pixel);
+	[lab[0] as f32, lab[1] as f32, lab[2] as f32]
+}
+
+#[cfg(test)]
+mod tests {
+	use image::{Luma, Rgb};
+
+	use super::*;
+	use crate::generator::utils::image::diff;
+
+	#[test]
+	fn test_diff_map() {
+		let target = &RgbImage::from_fn(8, 8, |x, _y| {
+			if x < 4 {
+				Rgb([0u8, 0u8, 0u8])
+			} else {
+				Rgb([255u8, 255u8, 255u8])
+			}
+		});
+		let mut current = RgbImage::from_fn(8, 8, |_x, _y| Rgb([255u8, 255u8, 255u8]));
+		let mut diff_map = DiffMap::new(&current, target, DiffMetric::Luma, None);
+		assert!((diff_map.diff() - diff(&current, target)).abs() < 1e-6);
+
+		// Patches that don't change anything keep the same difference
+		let patch = Patch::from_canvas(&current, 1, 1, 7, 7);
+		assert_eq!(diff_map.get_patch_diff(&patch, target), diff_map.diff());
+
+		// Painting black over the left half makes it identical
+		let mut patch = Patch::from_canvas(&current, 0, 0, 4, 8);
+		for x in 0..4 {
+			for y in 0..8 {
+				patch.put_pixel(x, y, Rgb([0u8, 0u8, 0u8]));
+			}
+		}
+		assert!(diff_map.get_patch_diff(&patch, target).abs() < 1e-6);
+
+		// Painting black over the right side makes it worse
+		let mut patch = Patch::from_canvas(&current, 6, 2, 8, 5);
+		for x in 6..8 {
+			for y in 2..5 {
+				patch.put_pixel(x, y, Rgb([0u8, 0u8, 0u8]));
+			}
+		}
+		let new_diff = diff_map.get_patch_diff(&patch, target);
+		assert!((new_diff - 38.0 / 64.0).abs() < 1e-6);
+
+		patch.apply(&mut current);
+		diff_map.apply(&patch, target);
+		assert_eq!(diff_map.diff(), new_diff);
+		assert!((diff_map.diff() - diff(&current, target)).abs() < 1e-6);
+	}
+
+	#[test]
+	fn test_diff_map_metrics() {
+		let white_img = &RgbImage::from_fn(4, 4, |_x, _y| Rgb([255u8, 255u8, 255u8]));
+		let black_img = &RgbImage::from_fn(4, 4, |_x, _y| Rgb([0u8, 0u8, 0u8]));
+		let red_img = &RgbImage::from_fn(4, 4, |_x, _y| Rgb([255u8, 0u8, 0u8]));
+
+		for metric in [DiffMetric::Luma, DiffMetric::LabDe76, DiffMetric::LabDe94, DiffMetric::LabDe2000] {
+			assert_eq!(DiffMap::new(white_img, white_img, metric, None).diff(), 0.0);
+			assert!((DiffMap::new(white_img, black_img, metric, None).diff() - 1.0).abs() < 1e-4);
+		}
+
+		// Red is perceptually further from black than its luma suggests
+		let luma_diff = DiffMap::new(red_img, black_img, DiffMetric::Luma, None).diff();
+		let lab_diff = DiffMap::new(red_img, black_img, DiffMetric::LabDe2000, None).diff();
+		assert!(lab_diff > luma_diff);
+
+		// Patches are diffed with the same metric
+		let mut diff_map = DiffMap::new(black_img, white_img, DiffMetric::LabDe2000, None);
+		let mut patch = Patch::from_canvas(black_img, 0, 0, 2, 4);
+		for x in 0..2 {
+			for y in 0..4 {
+				patch.put_pixel(x, y, Rgb([255u8, 255u8, 255u8]));
+			}
+		}
+		let new_diff = diff_map.get_patch_diff(&patch, white_img);
+		assert!((new_diff - 0.5).abs() < 1e-4);
+		diff_map.apply(&patch, white_img);
+		assert_eq!(diff_map.diff(), new_diff);
+	}
+
+	#[test]
+	fn test_diff_map_ssim() {
+		let target = &RgbImage::from_fn(16, 16, |x, y| {
+			if (x / 2 + y / 2) % 2 == 0 {
+				Rgb([0u8, 0u8, 0u8])
+			} else {
+				Rgb([255u8, 255u8, 255u8])
+			}
+		});
+		let gray_img = &RgbImage::from_fn(16, 16, |_x, _y| Rgb([128u8, 128u8, 128u8]));
+		let white_img = &RgbImage::from_fn(16, 16, |_x, _y| Rgb([255u8, 255u8, 255u8]));
+		let black_img = &RgbImage::from_fn(16, 16, |_x, _y| Rgb([0u8, 0u8, 0u8]));
+
+		assert_eq!(DiffMap::new(target, target, DiffMetric::Ssim, None).diff(), 0.0);
+		assert!((DiffMap::new(white_img, black_img, DiffMetric::Ssim, None).diff() - 1.0).abs() < 1e-3);
+
+		// A flat average has a lower absolute difference, but no structure
+		let gray_luma_diff = DiffMap::new(gray_img, target, DiffMetric::Luma, None).diff();
+		let gray_ssim_diff = DiffMap::new(gray_img, target, DiffMetric::Ssim, None).diff();
+		assert!(gray_luma_diff < 0.6);
+		assert!(gray_ssim_diff > 0.9);
+
+		// Patches change the pixels around them too
+		let mut current = gray_img.clone();
+		let mut diff_map = DiffMap::new(&current, target, DiffMetric::Ssim, None);
+		let patch = Patch::from_canvas(&current, 3, 3, 9, 9);
+		assert_eq!(diff_map.get_patch_diff(&patch, target), diff_map.diff());
+
+		let patch = Patch::from_canvas(target, 3, 3, 9, 9);
+		let new_diff = diff_map.get_patch_diff(&patch, target);
+		assert!(new_diff < diff_map.diff());
+
+		patch.apply(&mut current);
+		diff_map.apply(&patch, target);
+		assert_eq!(diff_map.diff(), new_diff);
+		assert!(
+			(diff_map.diff() - DiffMap::new(&current, target, DiffMetric::Ssim, None).diff()).abs() < 1e-6
+		);
+	}
+
+	#[test]
+	fn test_diff_map_importance_map() {
+		let target = &RgbImage::from_fn(4, 4, |_x, _y| Rgb([255u8, 255u8, 255u8]));
+		let current = &RgbImage::from_fn(4, 4, |x, _y| {
+			if x < 2 {
+				Rgb([0u8, 0u8, 0u8])
+			} else {
+				Rgb([255u8, 255u8, 255u8])
+			}
+		});
+
+		// Only the left half matters
+		let left_map = &GrayImage::from_fn(4, 4, |x, _y| {
+			if x < 2 {
+				Luma([255u8])
+			} else {
+				Luma([0u8])
+			}
+		});
+		let mut diff_map = DiffMap::new(current, target, DiffMetric::Luma, Some(left_map));
+		assert!((diff_map.diff() - 1.0).abs() < 1e-6);
+
+		// Only the right half matters
+		let right_map = &GrayImage::from_fn(4, 4, |x, _y| {
+			if x < 2 {
+				Luma([0u8])
+			} else {
+				Luma([255u8])
+			}
+		});
+		assert_eq!(DiffMap::new(current, target, DiffMetric::Luma, Some(right_map)).diff(), 0.0);
+
+		// Partial weights
+		let half_map = &GrayImage::from_fn(4, 4, |x, _y| {
+			if x < 2 {
+				Luma([255u8])
+			} else {
+				Luma([85u8])
+			}
+		});
+		assert!((DiffMap::new(current, target, DiffMetric::Luma, Some(half_map)).diff() - 0.75).abs() < 1e-6);
+
+		// Changes to pixels that don't matter are ignored
+		let mut patch = Patch::from_canvas(current, 2, 0, 4, 4);
+		patch.put_pixel(3, 3, Rgb([0u8, 0u8, 0u8]));
+		assert_eq!(diff_map.get_patch_diff(&patch, target), diff_map.diff());
+
+		let mut patch = Patch::from_canvas(current, 0, 0, 2, 2);
+		patch.put_pixel(1, 1, Rgb([255u8, 255u8, 255u8]));
+		let new_diff = diff_map.get_patch_diff(&patch, target);
+		assert!((new_diff - 7.0 / 8.0).abs() < 1e-6);
+		diff_map.apply(&patch, target);
+		assert_eq!(diff_map.diff(), new_diff);
+
+		// Nothing matters
+		let empty_map = &GrayImage::from_fn(4, 4, |_x, _y| Luma([0u8]));
+		assert_eq!(DiffMap::new(current, target, DiffMetric::Luma, Some(empty_map)).diff(), 0.0);
+	}
+
+	#[test]
+	fn test_diff_map_labels() {
+		let target = &RgbImage::from_fn(4, 4, |_x, _y| Rgb([255u8, 255u8, 255u8]));
+		let current = &RgbImage::from_fn(4, 4, |_x, _y| Rgb([0u8, 0u8, 0u8]));
+
+		// Top half is labelled 1, bottom half 0
+		let labels: Vec<u16> = (0..16)
+			.map(|index| {
+				if index < 8 {
+					1
+				} else {
+					0
+				}
+			})
+			.collect();
+		let mut diff_map = DiffMap::new(current, target, DiffMetric::Luma, None);
+		diff_map.set_labels(&labels, 3);
+		assert!((diff_map.get_label_error(0) - 8.0).abs() < 1e-6);
+		assert!((diff_map.get_label_error(1) - 8.0).abs() < 1e-6);
+		assert_eq!(diff_map.get_label_error(2), 0.0);
+
+		// Region errors follow committed patches
+		let mut patch = Patch::from_canvas(current, 0, 1, 4, 3);
+		for x in 0..4 {
+			for y in 1..3 {
+				patch.put_pixel(x, y, Rgb([255u8, 255u8, 255u8]));
+			}
+		}
+		diff_map.apply(&patch, target);
+		assert!((diff_map.get_label_error(0) - 4.0).abs() < 1e-6);
+		assert!((diff_map.get_label_error(1) - 4.0).abs() < 1e-6);
+
+		// Running sums can be restored
+		let (total, label_errors) = diff_map.get_sums();
+		let mut restored_diff_map = DiffMap::new(current, target, DiffMetric::Luma, None);
+		restored_diff_map.set_labels(&labels, 3);
+		restored_diff_map.set_sums(total, label_errors);
+		assert_eq!(restored_diff_map.diff(), diff_map.diff());
+		assert_eq!(restored_diff_map.get_label_error(0), diff_map.get_label_error(0));
+		assert_eq!(restored_diff_map.get_label_error(1), diff_map.get_label_error(1));
+	}
+}
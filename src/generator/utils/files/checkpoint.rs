@@ -0,0 +1,232 @@
+use std::fs;
+use std::path::Path;
+
+use image::RgbImage;
+use img_parts::png::{Png, PngChunk};
+use img_parts::Bytes;
+
+use crate::generator::utils::files::{generate_image, ImageFileFormat};
+use crate::generator::{PainterRun, ProcessState};
+
+// Keyword of the PNG text chunk with the checkpoint data
+const CHECKPOINT_KEYWORD: &str = "Checkpoint";
+
+/// A generation process saved to be resumed later: the command line arguments it was started with, where it
+/// was at, and its current image.
+pub struct Checkpoint {
+	pub args: Vec<String>,
+	pub state: ProcessState,
+	pub image: RgbImage,
+}
+
+impl Checkpoint {
+	/// Reads a checkpoint from a PNG file written by `write()`.
+	pub fn read(path: &Path) -> Result<Checkpoint, &'static str> {
+		let file_bytes = fs::read(path).map_err(|_| "Cannot read checkpoint file")?;
+		let image =
+			image::load_from_memory(&file_bytes).map_err(|_| "Cannot read checkpoint image")?.to_rgb8();
+		let png =
+			Png::from_bytes(Bytes::from(file_bytes)).map_err(|_| "Checkpoint file is not a PNG image")?;
+		let text = png
+			.chunks()
+			.iter()
+			.filter(|chunk| chunk.kind() == *b"iTXt")
+			.find_map(|chunk| get_international_text(chunk.contents(), CHECKPOINT_KEYWORD))
+			.ok_or("Checkpoint file has no checkpoint data")?;
+		let (args, state) = from_text(&text)?;
+
+		Ok(Checkpoint {
+			args,
+			state,
+			image,
+		})
+	}
+
+	/// Writes the checkpoint as a PNG image, with the arguments and state in a text chunk. The file is
+	/// written under a temporary name first, so an interrupted write doesn't overwrite a previous checkpoint.
+	pub fn write(&self, path: &Path) -> Result<(), &'static str> {
+		let image_bytes = generate_image(self.image.clone(), ImageFileFormat::PNG);
+		let mut png = Png::from_bytes(image_bytes).map_err(|_| "Cannot encode checkpoint image")?;
+		let contents =
+			format!("{}\u{0}\u{0}\u{0}\u{0}\u{0}{}", CHECKPOINT_KEYWORD, to_text(&self.args, &self.state));
+		let chunks = png.chunks_mut().len();
+		png.chunks_mut().insert(chunks - 1, PngChunk::new(*b"iTXt", Bytes::from(contents)));
+
+		let mut file_bytes = vec![];
+		png.encoder().write_to(&mut file_bytes).map_err(|_| "Cannot encode checkpoint image")?;
+		let mut temporary_path = path.as_os_str().to_owned();
+		temporary_path.push(".tmp");
+		fs::write(&temporary_path, file_bytes).map_err(|_| "Cannot write checkpoint file")?;
+		fs::rename(&temporary_path, path).map_err(|_| "Cannot write checkpoint file")
+	}
+}
+
+/// Reads the text of an uncompressed PNG international text chunk, if it has the given keyword.
+fn get_international_text(contents: &[u8], keyword: &str) -> Option<String> {
+	// Contents are the keyword, compression flag and method, language tag, translated keyword, and text
+	let keyword_end = contents.iter().position(|&byte| byte == 0)?;
+	if &contents[..keyword_end] != keyword.as_bytes() || contents.get(keyword_end + 1) != Some(&0) {
+		return None;
+	}
+	let mut parts = contents.get(keyword_end + 3..)?.splitn(3, |&byte| byte == 0);
+	parts.next()?;
+	parts.next()?;
+	String::from_utf8(parts.next()?.to_vec()).ok()
+}
+
+/// Converts the arguments and state to text, with one `key: value` line per value. Numbers with decimals
+/// are written in a format that reads back to the exact same values.
+fn to_text(args: &[String], state: &ProcessState) -> String {
+	let mut lines: Vec<String> = args.iter().map(|arg| format!("arg: {}", arg)).collect();
+	lines.push(format!("rng-seed: {}", state.rng_seed));
+	lines.push(format!("tries: {}", state.num_tries));
+	lines.push(format!("generations: {}", state.num_generations));
+	lines.push(format!("processes: {}", state.num_processes));
+	lines.push(format!("time-elapsed: {:e}", state.time_elapsed));
+	lines.push(format!("temperature: {:e}", state.temperature));
+	lines.push(format!(
+		"painter-generations: {}",
+		state
+			.painter_generations
+			.iter()
+			.map(|generations| generations
+				.iter()
+				.map(|value| value.to_string())
+				.collect::<Vec<String>>()
+				.join(","))
+			.collect::<Vec<String>>()
+			.join(";")
+	));
+	lines.push(format!(
+		"painter-runs: {}",
+		state
+			.painter_runs
+			.iter()
+			.map(|run| format!("{}.{}x{}", run.region, run.painter, run.generations))
+			.collect::<Vec<String>>()
+			.join(",")
+	));
+	lines.push(format!("diff-total: {:e}", state.diff_total));
+	lines.push(format!(
+		"label-errors: {}",
+		state.label_errors.iter().map(|value| format!("{:e}", value)).collect::<Vec<String>>().join(",")
+	));
+	lines.join("\n")
+}
+
+/// Reads the arguments and state from text written by `to_text()`.
+fn from_text(text: &str) -> Result<(Vec<String>, ProcessState), &'static str> {
+	let mut args = vec![];
+	let mut state = ProcessState::default();
+	for line in text.lines() {
+		let (key, value) = line.split_once(": ").ok_or("Invalid checkpoint line")?;
+		match key {
+			"arg" => args.push(value.to_string()),
+			"rng-seed" => state.rng_seed = value.parse().map_err(|_| "Invalid checkpoint RNG seed")?,
+			"tries" => state.num_tries = value.parse().map_err(|_| "Invalid checkpoint tries")?,
+			"generations" => {
+				state.num_generations = value.parse().map_err(|_| "Invalid checkpoint generations")?
+			}
+			"processes" => state.num_processes = value.parse().map_err(|_| "Invalid checkpoint processes")?,
+			"time-elapsed" => state.time_elapsed = value.parse().map_err(|_| "Invalid checkpoint time")?,
+			"temperature" => {
+				state.temperature = value.parse().map_err(|_| "Invalid checkpoint temperature")?
+			}
+			"painter-generations" => {
+				state.painter_generations = value
+					.split(';')
+					.map(|generations| {
+						generations
+							.split(',')
+							.filter(|value| !value.is_empty())
+							.map(|value| value.parse::<u32>())
+							.collect::<Result<Vec<u32>, _>>()
+					})
+					.collect::<Result<Vec<Vec<u32>>, _>>()
+					.map_err(|_| "Invalid checkpoint painter generations")?
+			}
+			"painter-runs" => {
+				state.painter_runs = value
+					.split(',')
+					.filter(|value| !value.is_empty())
+					.map(parse_painter_run)
+					.collect::<Option<Vec<PainterRun>>>()
+					.ok_or("Invalid checkpoint painter runs")?
+			}
+			"diff-total" => state.diff_total = value.parse().map_err(|_| "Invalid checkpoint difference")?,
+			"label-errors" => {
+				state.label_errors = value
+					.split(',')
+					.filter(|value| !value.is_empty())
+					.map(|value| value.parse::<f64>())
+					.collect::<Result<Vec<f64>, _>>()
+					.map_err(|_| "Invalid checkpoint region differences")?
+			}
+			_ => return Err("Unknown checkpoint key"),
+		}
+	}
+	if args.is_empty() {
+		return Err("Checkpoint has no arguments");
+	}
+	Ok((args, state))
+}
+
+/// Reads a run of generations of a painter written by `to_text()`, as `region.painter x generations`.
+fn parse_painter_run(text: &str) -> Option<PainterRun> {
+	let (painter, generations) = text.split_once('x')?;
+	let (region, painter) = painter.split_once('.')?;
+	Some(PainterRun {
+		region: region.parse().ok()?,
+		painter: painter.parse().ok()?,
+		generations: generations.parse().ok()?,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_checkpoint_text() {
+		let args = vec!["target image.png".to_string(), "--generations".to_string(), "100".to_string()];
+		let state = ProcessState {
+			rng_seed: 12345,
+			num_tries: 200,
+			num_generations: 50,
+			num_processes: 1600,
+			time_elapsed: 12.3456789,
+			temperature: 0.1 + 0.2,
+			painter_generations: vec![vec![20, 10], vec![], vec![20]],
+			diff_total: 1234.5678901234567,
+			label_errors: vec![1.0 / 3.0, 0.0, 1e-300],
+			painter_runs: vec![
+				PainterRun {
+					region: 0,
+					painter: 1,
+					generations: 12,
+				},
+				PainterRun {
+					region: 2,
+					painter: 0,
+					generations: 1,
+				},
+			],
+		};
+		assert_eq!(from_text(&to_text(&args, &state)), Ok((args, state)));
+
+		assert_eq!(from_text("arg: target.png\ntries: many"), Err("Invalid checkpoint tries"));
+		assert_eq!(from_text("arg: target.png\nfoo: 1"), Err("Unknown checkpoint key"));
+		assert_eq!(from_text("arg: target.png\npainter-runs: 0.1x"), Err("Invalid checkpoint painter runs"));
+		assert_eq!(from_text("tries: 1"), Err("Checkpoint has no arguments"));
+	}
+
+	#[test]
+	fn test_get_international_text() {
+		assert_eq!(
+			get_international_text(b"Checkpoint\0\0\0en\0\0text", "Checkpoint"),
+			Some("text".to_string())
+		);
+		assert_eq!(get_international_text(b"Comment\0\0\0\0\0text", "Checkpoint"), None);
+		assert_eq!(get_international_text(b"Checkpoint\0\x01\0\0\0text", "Checkpoint"), None);
+	}
+}
@@ -11,6 +11,8 @@ use img_parts::{
 };
 use structopt::clap::crate_version;
 
+pub mod checkpoint;
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum ImageFileFormat {
 	PNG,
@@ -0,0 +1,288 @@
+use std::f64::consts::PI;
+
+use image::RgbImage;
+use strum_macros::{Display, EnumString};
+
+use crate::generator::utils::geom::distance;
+use crate::generator::utils::image::get_pixel_interpolated;
+use crate::generator::utils::pixel::blend_linear;
+use crate::generator::utils::random::rng::Rng;
+use crate::generator::utils::random::{get_random_color, get_random_entry_weighted_lazy, get_random_range};
+use crate::generator::utils::units::{Rectangle, WeightedValue};
+
+#[derive(Clone, Debug, Default, Display, EnumString, PartialEq)]
+pub enum FillType {
+	#[default]
+	#[strum(serialize = "flat")]
+	Flat,
+	#[strum(serialize = "linear")]
+	Linear,
+	#[strum(serialize = "radial")]
+	Radial,
+}
+
+/// How a painted element is filled: with a single color, or with a gradient between two colors.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Fill {
+	Flat([u8; 3]),
+	Linear {
+		from: (f64, f64),
+		to: (f64, f64),
+		from_color: [u8; 3],
+		to_color: [u8; 3],
+	},
+	Radial {
+		center: (f64, f64),
+		radius: f64,
+		from_color: [u8; 3],
+		to_color: [u8; 3],
+	},
+}
+
+impl Fill {
+	/// Finds the color of the fill at a given pixel.
+	#[inline(always)]
+	pub fn get_color(&self, x: f64, y: f64) -> [u8; 3] {
+		match self {
+			Self::Flat(color) => *color,
+			Self::Linear {
+				from,
+				to,
+				from_color,
+				to_color,
+			} => {
+				let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+				let length_squared = dx * dx + dy * dy;
+				let position = if length_squared == 0.0 {
+					0.0
+				} else {
+					(((x - from.0) * dx + (y - from.1) * dy) / length_squared).clamp(0.0, 1.0)
+				};
+				blend_linear(from_color, to_color, position)
+			}
+			Self::Radial {
+				center,
+				radius,
+				from_color,
+				to_color,
+			} => {
+				let position = if *radius == 0.0 {
+					0.0
+				} else {
+					(distance(center.0, center.1, x, y) / radius).clamp(0.0, 1.0)
+				};
+				blend_linear(from_color, to_color, position)
+			}
+		}
+	}
+
+	/// The colors used by the fill, so they can be changed.
+	pub fn colors_mut(&mut self) -> Vec<&mut [u8; 3]> {
+		match self {
+			Self::Flat(color) => vec![color],
+			Self::Linear {
+				from_color,
+				to_color,
+				..
+			}
+			| Self::Radial {
+				from_color,
+				to_color,
+				..
+			} => vec![from_color, to_color],
+		}
+	}
+}
+
+/// Decides on a random fill for an element painted inside the given bounds.
+///
+/// Flat fills use the already decided `color`. Gradients start with it (for radial gradients), or
+/// with the same `random_color` mixed with the seed map at the start of the gradient (for linear
+/// gradients); they end with a new random color mixed with the seed map at the opposite end.
+pub fn get_random_fill(
+	rng: &mut Rng,
+	fill_types: &Vec<WeightedValue<FillType>>,
+	color: &[u8; 3],
+	random_color: &[u8; 3],
+	seed_map: &RgbImage,
+	color_seed: f64,
+	bounds: &Rectangle<f64>,
+) -> Fill {
+	let center = (bounds.x + bounds.width / 2.0, bounds.y + bounds.height / 2.0);
+	match get_random_entry_weighted_lazy(rng, fill_types) {
+		FillType::Flat => Fill::Flat(*color),
+		FillType::Linear => {
+			// Gradients go across the whole bounds, in any direction
+			let angle = get_random_range(rng, 0.0, PI * 2.0);
+			let (sin, cos) = angle.sin_cos();
+			let half_length = ((bounds.width * cos).abs() + (bounds.height * sin).abs()) / 2.0;
+			let from = (center.0 - cos * half_length, center.1 - sin * half_length);
+			let to = (center.0 + cos * half_length, center.1 + sin * half_length);
+			let from_seed_color = get_pixel_interpolated(seed_map, from.0, from.1);
+			let to_seed_color = get_pixel_interpolated(seed_map, to.0, to.1);
+			Fill::Linear {
+				from,
+				to,
+				from_color: blend_linear(random_color, &from_seed_color, color_seed),
+				to_color: blend_linear(&get_random_color(rng), &to_seed_color, color_seed),
+			}
+		}
+		FillType::Radial => {
+			// Gradients go from the center to the corners of the bounds
+			let radius = bounds.width.hypot(bounds.height) / 2.0;
+			let angle = get_random_range(rng, 0.0, PI * 2.0);
+			let (sin, cos) = angle.sin_cos();
+			let to_seed_color = get_pixel_interpolated(
+				seed_map,
+				center.0 + cos * bounds.width / 2.0,
+				center.1 + sin * bounds.height / 2.0,
+			);
+			Fill::Radial {
+				center,
+				radius,
+				from_color: *color,
+				to_color: blend_linear(&get_random_color(rng), &to_seed_color, color_seed),
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use image::Rgb;
+
+	use super::*;
+
+	#[test]
+	fn test_fill_get_color() {
+		let flat = Fill::Flat([10, 20, 30]);
+		assert_eq!(flat.get_color(0.0, 0.0), [10, 20, 30]);
+		assert_eq!(flat.get_color(100.0, -5.0), [10, 20, 30]);
+
+		let linear = Fill::Linear {
+			from: (0.0, 0.0),
+			to: (10.0, 0.0),
+			from_color: [0, 0, 0],
+			to_color: [200, 100, 0],
+		};
+		assert_eq!(linear.get_color(0.0, 0.0), [0, 0, 0]);
+		assert_eq!(linear.get_color(5.0, 7.0), [100, 50, 0]);
+		assert_eq!(linear.get_color(10.0, -3.0), [200, 100, 0]);
+		assert_eq!(linear.get_color(-5.0, 0.0), [0, 0, 0]);
+		assert_eq!(linear.get_color(20.0, 0.0), [200, 100, 0]);
+
+		let radial = Fill::Radial {
+			center: (5.0, 5.0),
+			radius: 4.0,
+			from_color: [200, 0, 0],
+			to_color: [0, 0, 200],
+		};
+		assert_eq!(radial.get_color(5.0, 5.0), [200, 0, 0]);
+		assert_eq!(radial.get_color(5.0, 7.0), [100, 0, 100]);
+		assert_eq!(radial.get_color(1.0, 5.0), [0, 0, 200]);
+		assert_eq!(radial.get_color(20.0, 20.0), [0, 0, 200]);
+	}
+
+	#[test]
+	fn test_fill_colors_mut() {
+		let mut flat = Fill::Flat([10, 20, 30]);
+		for color in flat.colors_mut() {
+			color[0] = 0;
+		}
+		assert_eq!(flat, Fill::Flat([0, 20, 30]));
+
+		let mut linear = Fill::Linear {
+			from: (0.0, 0.0),
+			to: (10.0, 0.0),
+			from_color: [0, 0, 0],
+			to_color: [200, 100, 0],
+		};
+		for color in linear.colors_mut() {
+			color[2] = 50;
+		}
+		assert_eq!(linear.get_color(0.0, 0.0), [0, 0, 50]);
+		assert_eq!(linear.get_color(10.0, 0.0), [200, 100, 50]);
+	}
+
+	#[test]
+	fn test_get_random_fill() {
+		let seed_map = &RgbImage::from_fn(10, 10, |x, _y| {
+			if x < 5 {
+				Rgb([0, 0, 0])
+			} else {
+				Rgb([255, 255, 255])
+			}
+		});
+		let bounds = Rectangle {
+			x: 0.0,
+			y: 0.0,
+			width: 10.0,
+			height: 10.0,
+		};
+		let weighted = |value: FillType| {
+			vec![WeightedValue {
+				value,
+				weight: 1.0,
+			}]
+		};
+		let mut rng = Rng::from_seed(1);
+
+		assert_eq!(
+			get_random_fill(
+				&mut rng,
+				&weighted(FillType::Flat),
+				&[1, 2, 3],
+				&[4, 5, 6],
+				seed_map,
+				1.0,
+				&bounds
+			),
+			Fill::Flat([1, 2, 3])
+		);
+
+		// Fully seeded gradients use the seed map at their ends
+		match get_random_fill(
+			&mut rng,
+			&weighted(FillType::Linear),
+			&[1, 2, 3],
+			&[4, 5, 6],
+			seed_map,
+			1.0,
+			&bounds,
+		) {
+			Fill::Linear {
+				from,
+				to,
+				from_color,
+				to_color,
+			} => {
+				assert!((distance(from.0, from.1, 5.0, 5.0) - distance(to.0, to.1, 5.0, 5.0)).abs() < 1e-9);
+				assert_eq!(from_color, get_pixel_interpolated(seed_map, from.0, from.1));
+				assert_eq!(to_color, get_pixel_interpolated(seed_map, to.0, to.1));
+			}
+			fill => panic!("expected linear fill, got {:?}", fill),
+		}
+
+		match get_random_fill(
+			&mut rng,
+			&weighted(FillType::Radial),
+			&[1, 2, 3],
+			&[4, 5, 6],
+			seed_map,
+			1.0,
+			&bounds,
+		) {
+			Fill::Radial {
+				center,
+				radius,
+				from_color,
+				..
+			} => {
+				assert_eq!(center, (5.0, 5.0));
+				assert!((radius - 50.0f64.sqrt()).abs() < 1e-9);
+				assert_eq!(from_color, [1, 2, 3]);
+			}
+			fill => panic!("expected radial fill, got {:?}", fill),
+		}
+	}
+}
@@ -7,6 +7,197 @@ pub fn distance(x1: f64, y1: f64, x2: f64, y2: f64) -> f64 {
 	(x * x + y * y).sqrt()
 }
 
+/// Rotates a point around an origin by the given angle, in radians.
+#[inline(always)]
+pub fn rotate_point(x: f64, y: f64, origin_x: f64, origin_y: f64, angle: f64) -> (f64, f64) {
+	let (sin, cos) = angle.sin_cos();
+	let dx = x - origin_x;
+	let dy = y - origin_y;
+	(origin_x + dx * cos - dy * sin, origin_y + dx * sin + dy * cos)
+}
+
+/// Converts the distance from a pixel to the closest edge of a shape (positive when inside) into
+/// the opacity that pixel should be painted with. With anti-alias, pixels within one pixel of the
+/// edge are partially transparent; without it, they're either fully opaque or fully transparent.
+#[inline(always)]
+pub fn get_edge_alpha(inner_distance: f64, anti_alias: bool) -> f64 {
+	if inner_distance <= 0.0 {
+		0.0
+	} else if inner_distance > 1.0 {
+		1.0
+	} else if anti_alias {
+		inner_distance
+	} else if inner_distance >= 0.5 {
+		1.0
+	} else {
+		0.0
+	}
+}
+
+/// Converts the signed distance from a pixel to a straight edge (positive when inside) into the
+/// fraction of that pixel covered by the shape, assuming the edge crosses the pixel's center.
+#[inline(always)]
+pub fn get_edge_coverage(inner_offset: f64) -> f64 {
+	(inner_offset + 0.5).clamp(0.0, 1.0)
+}
+
+/// Fraction of a pixel covered by an axis-aligned rectangle.
+#[inline(always)]
+pub fn rect_coverage(x: f64, y: f64, x1: f64, y1: f64, x2: f64, y2: f64) -> f64 {
+	get_edge_coverage(x - x1)
+		* get_edge_coverage(x2 - x)
+		* get_edge_coverage(y - y1)
+		* get_edge_coverage(y2 - y)
+}
+
+/// Length of the overlap between a pixel (centered at a given coordinate) and a span along the
+/// same axis. Unlike `get_edge_coverage()`, this is also correct for spans thinner than a pixel.
+#[inline(always)]
+pub fn span_coverage(x: f64, x1: f64, x2: f64) -> f64 {
+	((x + 0.5).min(x2) - (x - 0.5).max(x1)).clamp(0.0, 1.0)
+}
+
+/// Half of the width and height of the axis-aligned box containing a rotated rectangle.
+pub fn rect_extents(width: f64, height: f64, angle: f64) -> (f64, f64) {
+	let (sin, cos) = angle.sin_cos();
+	(((width * cos).abs() + (height * sin).abs()) / 2.0, ((width * sin).abs() + (height * cos).abs()) / 2.0)
+}
+
+/// Signed distance from a point to the infinite line passing through two other points.
+/// The result is positive when the point is on the left side of the line (going from the first
+/// to the second point), negative when on the right side, and zero when on the line itself.
+#[inline(always)]
+pub fn signed_distance_to_line(x: f64, y: f64, x1: f64, y1: f64, x2: f64, y2: f64) -> f64 {
+	let length = distance(x1, y1, x2, y2);
+	if length == 0.0 {
+		return -distance(x, y, x1, y1);
+	}
+	((x2 - x1) * (y - y1) - (y2 - y1) * (x - x1)) / length
+}
+
+/// Twice the signed area of a triangle; positive when the vertices are in counter-clockwise order
+/// (in image coordinates, with y pointing down), negative when clockwise.
+#[inline(always)]
+pub fn triangle_signed_area_2x(p1: (f64, f64), p2: (f64, f64), p3: (f64, f64)) -> f64 {
+	(p2.0 - p1.0) * (p3.1 - p1.1) - (p3.0 - p1.0) * (p2.1 - p1.1)
+}
+
+/// Distance from a point to the closest edge of a triangle; positive when inside, negative when outside.
+pub fn triangle_inner_distance(x: f64, y: f64, p1: (f64, f64), p2: (f64, f64), p3: (f64, f64)) -> f64 {
+	// Normalize winding so the inside is always on the left of each edge
+	let (p2, p3) = if triangle_signed_area_2x(p1, p2, p3) < 0.0 {
+		(p3, p2)
+	} else {
+		(p2, p3)
+	};
+	let d1 = signed_distance_to_line(x, y, p1.0, p1.1, p2.0, p2.1);
+	let d2 = signed_distance_to_line(x, y, p2.0, p2.1, p3.0, p3.1);
+	let d3 = signed_distance_to_line(x, y, p3.0, p3.1, p1.0, p1.1);
+	d1.min(d2).min(d3)
+}
+
+/// Distance from a point to the closest point of a line segment.
+#[inline(always)]
+pub fn distance_to_segment(x: f64, y: f64, x1: f64, y1: f64, x2: f64, y2: f64) -> f64 {
+	project_to_segment(x, y, x1, y1, x2, y2).0
+}
+
+/// Finds the closest point of a line segment to another point. Returns the distance to it, and its
+/// position along the segment (`0.0` at the start, `1.0` at the end).
+#[inline(always)]
+pub fn project_to_segment(x: f64, y: f64, x1: f64, y1: f64, x2: f64, y2: f64) -> (f64, f64) {
+	let dx = x2 - x1;
+	let dy = y2 - y1;
+	let length_sq = dx * dx + dy * dy;
+	if length_sq == 0.0 {
+		return (distance(x, y, x1, y1), 0.0);
+	}
+	let t = (((x - x1) * dx + (y - y1) * dy) / length_sq).clamp(0.0, 1.0);
+	(distance(x, y, x1 + t * dx, y1 + t * dy), t)
+}
+
+/// Point at a given position (`0.0`..`1.0`) of a cubic Bezier curve.
+#[inline(always)]
+pub fn cubic_bezier_point(
+	p0: (f64, f64),
+	p1: (f64, f64),
+	p2: (f64, f64),
+	p3: (f64, f64),
+	t: f64,
+) -> (f64, f64) {
+	let mt = 1.0 - t;
+	let a = mt * mt * mt;
+	let b = 3.0 * mt * mt * t;
+	let c = 3.0 * mt * t * t;
+	let d = t * t * t;
+	(a * p0.0 + b * p1.0 + c * p2.0 + d * p3.0, a * p0.1 + b * p1.1 + c * p2.1 + d * p3.1)
+}
+
+/// Distance from a point to the closest edge of a closed polygon.
+pub fn polygon_edge_distance(x: f64, y: f64, vertices: &[(f64, f64)]) -> f64 {
+	let mut min_distance = f64::MAX;
+	for i in 0..vertices.len() {
+		let p1 = vertices[i];
+		let p2 = vertices[(i + 1) % vertices.len()];
+		min_distance = min_distance.min(distance_to_segment(x, y, p1.0, p1.1, p2.0, p2.1));
+	}
+	min_distance
+}
+
+/// Finds all horizontal spans of a closed polygon that are inside it at a given scanline,
+/// using the even-odd rule. Spans are returned as (start, end) pairs, sorted from left to right.
+pub fn polygon_scanline_spans(y: f64, vertices: &[(f64, f64)]) -> Vec<(f64, f64)> {
+	let mut crossings = Vec::<f64>::new();
+	for i in 0..vertices.len() {
+		let p1 = vertices[i];
+		let p2 = vertices[(i + 1) % vertices.len()];
+		// Half-open test so vertices shared by two edges are only counted once
+		if (p1.1 <= y && p2.1 > y) || (p2.1 <= y && p1.1 > y) {
+			crossings.push(p1.0 + (y - p1.1) / (p2.1 - p1.1) * (p2.0 - p1.0));
+		}
+	}
+	crossings.sort_by(|a, b| a.partial_cmp(b).expect("comparing scanline crossings"));
+	crossings.chunks_exact(2).map(|pair| (pair[0], pair[1])).collect()
+}
+
+/// Half of the width and height of the axis-aligned box containing a rotated ellipse.
+pub fn ellipse_extents(radius_x: f64, radius_y: f64, angle: f64) -> (f64, f64) {
+	let (sin, cos) = angle.sin_cos();
+	(
+		((radius_x * cos).powi(2) + (radius_y * sin).powi(2)).sqrt(),
+		((radius_x * sin).powi(2) + (radius_y * cos).powi(2)).sqrt(),
+	)
+}
+
+/// Approximate distance from a point to the edge of a rotated ellipse, measured along the line from
+/// the center of the ellipse; positive when inside, negative when outside. For circles, this is exact.
+pub fn ellipse_inner_distance(
+	x: f64,
+	y: f64,
+	center_x: f64,
+	center_y: f64,
+	radius_x: f64,
+	radius_y: f64,
+	angle: f64,
+) -> f64 {
+	if radius_x <= 0.0 || radius_y <= 0.0 {
+		return -distance(x, y, center_x, center_y);
+	}
+
+	// Move the point to the ellipse's own coordinate space
+	let (u, v) = rotate_point(x, y, center_x, center_y, -angle);
+	let u = u - center_x;
+	let v = v - center_y;
+	let dist = (u * u + v * v).sqrt();
+	if dist == 0.0 {
+		return radius_x.min(radius_y);
+	}
+
+	// Radius of the ellipse in the direction of the point
+	let f = ((u / radius_x).powi(2) + (v / radius_y).powi(2)).sqrt();
+	dist / f - dist
+}
+
 pub fn find_target_draw_rect(
 	dimensions: (u32, u32),
 	margins: &Margins<SizeUnit>,
@@ -25,3 +216,190 @@ pub fn find_target_draw_rect(
 		Ok(rect)
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use std::f64::consts::PI;
+
+	use super::*;
+
+	#[test]
+	fn test_get_edge_alpha() {
+		assert_eq!(get_edge_alpha(-1.0, true), 0.0);
+		assert_eq!(get_edge_alpha(0.0, true), 0.0);
+		assert_eq!(get_edge_alpha(0.25, true), 0.25);
+		assert_eq!(get_edge_alpha(0.75, true), 0.75);
+		assert_eq!(get_edge_alpha(1.5, true), 1.0);
+
+		assert_eq!(get_edge_alpha(-1.0, false), 0.0);
+		assert_eq!(get_edge_alpha(0.25, false), 0.0);
+		assert_eq!(get_edge_alpha(0.5, false), 1.0);
+		assert_eq!(get_edge_alpha(0.75, false), 1.0);
+		assert_eq!(get_edge_alpha(1.5, false), 1.0);
+	}
+
+	#[test]
+	fn test_get_edge_coverage() {
+		assert_eq!(get_edge_coverage(-1.0), 0.0);
+		assert_eq!(get_edge_coverage(-0.5), 0.0);
+		assert_eq!(get_edge_coverage(-0.25), 0.25);
+		assert_eq!(get_edge_coverage(0.0), 0.5);
+		assert_eq!(get_edge_coverage(0.25), 0.75);
+		assert_eq!(get_edge_coverage(2.0), 1.0);
+	}
+
+	#[test]
+	fn test_rect_coverage() {
+		assert_eq!(rect_coverage(5.0, 5.0, 0.0, 0.0, 10.0, 10.0), 1.0);
+		assert_eq!(rect_coverage(0.0, 5.0, 0.0, 0.0, 10.0, 10.0), 0.5);
+		assert_eq!(rect_coverage(0.0, 0.0, 0.0, 0.0, 10.0, 10.0), 0.25);
+		assert_eq!(rect_coverage(11.0, 5.0, 0.0, 0.0, 10.0, 10.0), 0.0);
+	}
+
+	#[test]
+	fn test_span_coverage() {
+		assert_eq!(span_coverage(5.0, 0.0, 10.0), 1.0);
+		assert_eq!(span_coverage(0.0, 0.0, 10.0), 0.5);
+		assert_eq!(span_coverage(5.0, 4.75, 5.25), 0.5);
+		assert_eq!(span_coverage(5.0, 5.25, 6.0), 0.25);
+		assert_eq!(span_coverage(11.0, 0.0, 10.0), 0.0);
+	}
+
+	#[test]
+	fn test_rect_extents() {
+		assert_eq!(rect_extents(10.0, 4.0, 0.0), (5.0, 2.0));
+		let (w, h) = rect_extents(10.0, 4.0, PI / 2.0);
+		assert!((w - 2.0).abs() < 1e-9);
+		assert!((h - 5.0).abs() < 1e-9);
+		let (w, h) = rect_extents(2.0, 2.0, PI / 4.0);
+		assert!((w - 2.0f64.sqrt()).abs() < 1e-9);
+		assert!((h - 2.0f64.sqrt()).abs() < 1e-9);
+	}
+
+	#[test]
+	fn test_signed_distance_to_line() {
+		// Horizontal line going right; in image coordinates, "left" is below it
+		assert_eq!(signed_distance_to_line(5.0, 2.0, 0.0, 0.0, 10.0, 0.0), 2.0);
+		assert_eq!(signed_distance_to_line(5.0, -3.0, 0.0, 0.0, 10.0, 0.0), -3.0);
+		assert_eq!(signed_distance_to_line(20.0, 0.0, 0.0, 0.0, 10.0, 0.0), 0.0);
+
+		// Degenerate line
+		assert_eq!(signed_distance_to_line(3.0, 4.0, 0.0, 0.0, 0.0, 0.0), -5.0);
+	}
+
+	#[test]
+	fn test_rotate_point() {
+		let (x, y) = rotate_point(10.0, 5.0, 5.0, 5.0, PI / 2.0);
+		assert!((x - 5.0).abs() < 1e-9);
+		assert!((y - 10.0).abs() < 1e-9);
+
+		let (x, y) = rotate_point(10.0, 5.0, 5.0, 5.0, PI);
+		assert!((x - 0.0).abs() < 1e-9);
+		assert!((y - 5.0).abs() < 1e-9);
+	}
+
+	#[test]
+	fn test_ellipse_extents() {
+		assert_eq!(ellipse_extents(10.0, 5.0, 0.0), (10.0, 5.0));
+		let (w, h) = ellipse_extents(10.0, 5.0, PI / 2.0);
+		assert!((w - 5.0).abs() < 1e-9);
+		assert!((h - 10.0).abs() < 1e-9);
+		let (w, h) = ellipse_extents(4.0, 4.0, 1.234);
+		assert!((w - 4.0).abs() < 1e-9);
+		assert!((h - 4.0).abs() < 1e-9);
+	}
+
+	#[test]
+	fn test_ellipse_inner_distance() {
+		// Circles
+		assert_eq!(ellipse_inner_distance(0.0, 0.0, 0.0, 0.0, 5.0, 5.0, 0.0), 5.0);
+		assert_eq!(ellipse_inner_distance(3.0, 0.0, 0.0, 0.0, 5.0, 5.0, 0.0), 2.0);
+		assert_eq!(ellipse_inner_distance(0.0, 7.0, 0.0, 0.0, 5.0, 5.0, 0.0), -2.0);
+
+		// Ellipses
+		assert_eq!(ellipse_inner_distance(8.0, 0.0, 0.0, 0.0, 10.0, 5.0, 0.0), 2.0);
+		assert_eq!(ellipse_inner_distance(0.0, 4.0, 0.0, 0.0, 10.0, 5.0, 0.0), 1.0);
+		assert!((ellipse_inner_distance(0.0, 8.0, 0.0, 0.0, 10.0, 5.0, PI / 2.0) - 2.0).abs() < 1e-9);
+		assert!((ellipse_inner_distance(4.0, 0.0, 0.0, 0.0, 10.0, 5.0, PI / 2.0) - 1.0).abs() < 1e-9);
+	}
+
+	#[test]
+	fn test_distance_to_segment() {
+		assert_eq!(distance_to_segment(5.0, 3.0, 0.0, 0.0, 10.0, 0.0), 3.0);
+		assert_eq!(distance_to_segment(-3.0, 4.0, 0.0, 0.0, 10.0, 0.0), 5.0);
+		assert_eq!(distance_to_segment(13.0, -4.0, 0.0, 0.0, 10.0, 0.0), 5.0);
+		assert_eq!(distance_to_segment(3.0, 4.0, 0.0, 0.0, 0.0, 0.0), 5.0);
+	}
+
+	#[test]
+	fn test_project_to_segment() {
+		assert_eq!(project_to_segment(5.0, 3.0, 0.0, 0.0, 10.0, 0.0), (3.0, 0.5));
+		assert_eq!(project_to_segment(-3.0, 4.0, 0.0, 0.0, 10.0, 0.0), (5.0, 0.0));
+		assert_eq!(project_to_segment(13.0, -4.0, 0.0, 0.0, 10.0, 0.0), (5.0, 1.0));
+		assert_eq!(project_to_segment(3.0, 4.0, 0.0, 0.0, 0.0, 0.0), (5.0, 0.0));
+	}
+
+	#[test]
+	fn test_cubic_bezier_point() {
+		let p0 = (0.0, 0.0);
+		let p1 = (0.0, 10.0);
+		let p2 = (10.0, 10.0);
+		let p3 = (10.0, 0.0);
+		assert_eq!(cubic_bezier_point(p0, p1, p2, p3, 0.0), p0);
+		assert_eq!(cubic_bezier_point(p0, p1, p2, p3, 1.0), p3);
+		assert_eq!(cubic_bezier_point(p0, p1, p2, p3, 0.5), (5.0, 7.5));
+
+		// Straight lines
+		assert_eq!(cubic_bezier_point((0.0, 0.0), (1.0, 1.0), (2.0, 2.0), (3.0, 3.0), 0.5), (1.5, 1.5));
+	}
+
+	#[test]
+	fn test_polygon_edge_distance() {
+		let square = [(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)];
+		assert_eq!(polygon_edge_distance(5.0, 5.0, &square), 5.0);
+		assert_eq!(polygon_edge_distance(2.0, 5.0, &square), 2.0);
+		assert_eq!(polygon_edge_distance(5.0, 12.0, &square), 2.0);
+	}
+
+	#[test]
+	fn test_polygon_scanline_spans() {
+		let square = [(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)];
+		assert_eq!(polygon_scanline_spans(5.0, &square), vec![(0.0, 10.0)]);
+		assert_eq!(polygon_scanline_spans(-1.0, &square), vec![]);
+		assert_eq!(polygon_scanline_spans(10.0, &square), vec![]);
+
+		// Concave "U" shape has two spans near its top
+		let u_shape = [
+			(0.0, 0.0),
+			(2.0, 0.0),
+			(2.0, 8.0),
+			(8.0, 8.0),
+			(8.0, 0.0),
+			(10.0, 0.0),
+			(10.0, 10.0),
+			(0.0, 10.0),
+		];
+		assert_eq!(polygon_scanline_spans(4.0, &u_shape), vec![(0.0, 2.0), (8.0, 10.0)]);
+		assert_eq!(polygon_scanline_spans(9.0, &u_shape), vec![(0.0, 10.0)]);
+
+		// Self-intersecting "bowtie" uses the even-odd rule
+		let star = [(0.0, 0.0), (10.0, 10.0), (10.0, 0.0), (0.0, 10.0)];
+		assert_eq!(polygon_scanline_spans(2.0, &star), vec![(0.0, 2.0), (8.0, 10.0)]);
+	}
+
+	#[test]
+	fn test_triangle_inner_distance() {
+		let p1 = (0.0, 0.0);
+		let p2 = (10.0, 0.0);
+		let p3 = (0.0, 10.0);
+
+		assert_eq!(triangle_inner_distance(1.0, 1.0, p1, p2, p3), 1.0);
+		assert_eq!(triangle_inner_distance(2.0, 5.0, p1, p2, p3), 2.0);
+		assert_eq!(triangle_inner_distance(-1.0, 5.0, p1, p2, p3), -1.0);
+		assert!(triangle_inner_distance(10.0, 10.0, p1, p2, p3) < 0.0);
+
+		// Winding order should not matter
+		assert_eq!(triangle_inner_distance(1.0, 1.0, p1, p3, p2), 1.0);
+		assert_eq!(triangle_inner_distance(-1.0, 5.0, p3, p2, p1), -1.0);
+	}
+}
@@ -1,6 +1,6 @@
 use std::convert::TryInto;
 
-use image::{imageops, GenericImageView, ImageBuffer, Pixel, RgbImage};
+use image::{imageops, DynamicImage, GenericImageView, GrayImage, ImageBuffer, Luma, Pixel, RgbImage};
 
 use crate::generator::utils::pixel;
 
@@ -11,6 +11,9 @@ const LUMA_R: f64 = 0.2126;
 const LUMA_G: f64 = 0.7152;
 const LUMA_B: f64 = 0.0722;
 
+/// Finds the luma-weighted difference of two whole images. Generation uses `DiffMap` instead, which keeps
+/// track of differences as the image changes, and supports other metrics; this is only kept to test it.
+#[cfg(test)]
 pub fn diff(a: &RgbImage, b: &RgbImage) -> f64 {
 	let w = a.dimensions().0;
 	let h = a.dimensions().1;
@@ -39,6 +42,15 @@ pub fn diff(a: &RgbImage, b: &RgbImage) -> f64 {
 	diff_sum / (num_pixels as f64 / skip_step as f64)
 }
 
+/// Returns the difference between two pixels, in the same scale as `diff()`.
+#[inline(always)]
+pub fn diff_pixel(a: &[u8], b: &[u8]) -> f64 {
+	((a[0] as i32 - b[0] as i32).abs() as f64 * LUMA_R
+		+ (a[1] as i32 - b[1] as i32).abs() as f64 * LUMA_G
+		+ (a[2] as i32 - b[2] as i32).abs() as f64 * LUMA_B)
+		/ 255.0
+}
+
 pub fn color_transform(image: &RgbImage, matrix: [f64; 12]) -> RgbImage {
 	let mut transformed_image = image.clone();
 	for (_x, _y, pixel) in transformed_image.enumerate_pixels_mut() {
@@ -103,6 +115,56 @@ pub fn get_pixel_interpolated(image: &RgbImage, x: f64, y: f64) -> [u8; 3] {
 	return pixel::blend_linear(&color_t, &color_b, yf);
 }
 
+/// Returns the luma (perceived brightness) of a pixel, in the `0.0`..`1.0` range.
+#[inline(always)]
+pub fn get_luma(pixel: &[u8]) -> f64 {
+	(pixel[0] as f64 * LUMA_R + pixel[1] as f64 * LUMA_G + pixel[2] as f64 * LUMA_B) / 255.0
+}
+
+/// Finds the luma gradient of an image at a given pixel, using a Sobel operator. Returns the
+/// horizontal and vertical rate of change, in luma units (`0.0`..`1.0`) per pixel.
+pub fn get_luma_gradient(image: &RgbImage, x: u32, y: u32) -> (f64, f64) {
+	let (width, height) = image.dimensions();
+	let luma = |dx: i64, dy: i64| -> f64 {
+		let px = (x as i64 + dx).max(0).min(width as i64 - 1) as u32;
+		let py = (y as i64 + dy).max(0).min(height as i64 - 1) as u32;
+		get_luma(image.get_pixel(px, py).channels())
+	};
+	let gx = (luma(1, -1) + 2.0 * luma(1, 0) + luma(1, 1)) - (luma(-1, -1) + 2.0 * luma(-1, 0) + luma(-1, 1));
+	let gy = (luma(-1, 1) + 2.0 * luma(0, 1) + luma(1, 1)) - (luma(-1, -1) + 2.0 * luma(0, -1) + luma(1, -1));
+	(gx / 8.0, gy / 8.0)
+}
+
+/// Converts an image to a mask that can be used as the alpha of a paint, where white opaque pixels
+/// are fully painted, and black or transparent pixels are not painted at all.
+pub fn to_alpha_mask(image: &DynamicImage) -> GrayImage {
+	let luma_alpha = image.to_luma_alpha8();
+	GrayImage::from_fn(luma_alpha.width(), luma_alpha.height(), |x, y| {
+		let pixel = luma_alpha.get_pixel(x, y).channels();
+		Luma([(pixel[0] as u32 * pixel[1] as u32 / 255) as u8])
+	})
+}
+
+/// Reads the value of a mask at any position, with bilinear interpolation, in the `0.0`..`1.0` range.
+/// Everything outside of the mask is treated as `0.0`, so edges fade out smoothly.
+pub fn get_mask_value_interpolated(mask: &GrayImage, x: f64, y: f64) -> f64 {
+	let (width, height) = mask.dimensions();
+	let value = |px: f64, py: f64| -> f64 {
+		if px < 0.0 || py < 0.0 || px >= width as f64 || py >= height as f64 {
+			0.0
+		} else {
+			mask.get_pixel(px as u32, py as u32).channels()[0] as f64 / 255.0
+		}
+	};
+	let x1 = x.floor();
+	let y1 = y.floor();
+	let xf = x - x1;
+	let yf = y - y1;
+	let value_t = value(x1, y1) * (1.0 - xf) + value(x1 + 1.0, y1) * xf;
+	let value_b = value(x1, y1 + 1.0) * (1.0 - xf) + value(x1 + 1.0, y1 + 1.0) * xf;
+	value_t * (1.0 - yf) + value_b * yf
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -137,6 +199,15 @@ mod tests {
 		assert_eq!(diff(&black_img, &blue_img), LUMA_B);
 	}
 
+	#[test]
+	fn test_diff_pixel() {
+		assert_eq!(diff_pixel(&[255, 255, 255], &[255, 255, 255]), 0.0);
+		assert_eq!(diff_pixel(&[0, 0, 0], &[255, 0, 0]), LUMA_R);
+		assert_eq!(diff_pixel(&[255, 255, 255], &[255, 0, 255]), LUMA_G);
+		assert_eq!(diff_pixel(&[0, 0, 255], &[0, 0, 0]), LUMA_B);
+		assert!((diff_pixel(&[0, 0, 0], &[255, 255, 255]) - 1.0).abs() < 1e-9);
+	}
+
 	#[test]
 	fn test_color_transform() {
 		let white_img = &RgbImage::from_fn(8, 8, |_x, _y| Rgb([255u8, 255u8, 255u8]));
@@ -170,6 +241,46 @@ mod tests {
 		assert_eq!(scale(img, 1.01).dimensions(), (8, 8));
 	}
 
+	#[test]
+	fn test_get_luma() {
+		assert_eq!(get_luma(&[0, 0, 0]), 0.0);
+		assert!((get_luma(&[255, 255, 255]) - 1.0).abs() < 1e-9);
+		assert_eq!(get_luma(&[255, 0, 0]), LUMA_R);
+		assert_eq!(get_luma(&[0, 255, 0]), LUMA_G);
+		assert_eq!(get_luma(&[0, 0, 255]), LUMA_B);
+	}
+
+	#[test]
+	fn test_get_luma_gradient() {
+		let flat_img = &RgbImage::from_fn(8, 8, |_x, _y| Rgb([128u8, 128u8, 128u8]));
+		assert_eq!(get_luma_gradient(flat_img, 4, 4), (0.0, 0.0));
+
+		// Horizontal ramp: black on the left half, white on the right half
+		let h_edge_img = &RgbImage::from_fn(8, 8, |x, _y| {
+			if x < 4 {
+				Rgb([0u8, 0u8, 0u8])
+			} else {
+				Rgb([255u8, 255u8, 255u8])
+			}
+		});
+		let (gx, gy) = get_luma_gradient(h_edge_img, 4, 4);
+		assert!((gx - 0.5).abs() < 1e-9);
+		assert_eq!(gy, 0.0);
+		assert_eq!(get_luma_gradient(h_edge_img, 0, 4), (0.0, 0.0));
+
+		// Vertical edge: white on top, black on the bottom
+		let v_edge_img = &RgbImage::from_fn(8, 8, |_x, y| {
+			if y < 4 {
+				Rgb([255u8, 255u8, 255u8])
+			} else {
+				Rgb([0u8, 0u8, 0u8])
+			}
+		});
+		let (gx, gy) = get_luma_gradient(v_edge_img, 4, 3);
+		assert_eq!(gx, 0.0);
+		assert!((gy + 0.5).abs() < 1e-9);
+	}
+
 	#[test]
 	fn test_get_pixel_interpolated() {
 		let img = &RgbImage::from_raw(
@@ -209,4 +320,39 @@ mod tests {
 
 		assert_eq!(get_pixel_interpolated(img, 9.1f64, 8.2f64), [255u8, 0u8, 128u8]);
 	}
+
+	#[test]
+	fn test_to_alpha_mask() {
+		let img = DynamicImage::ImageLumaA8(ImageBuffer::from_fn(3, 1, |x, _y| match x {
+			0 => image::LumaA([255u8, 255u8]),
+			1 => image::LumaA([255u8, 0u8]),
+			_ => image::LumaA([128u8, 255u8]),
+		}));
+		let mask = to_alpha_mask(&img);
+		assert_eq!(mask.get_pixel(0, 0), &Luma([255u8]));
+		assert_eq!(mask.get_pixel(1, 0), &Luma([0u8]));
+		assert_eq!(mask.get_pixel(2, 0), &Luma([128u8]));
+	}
+
+	#[test]
+	fn test_get_mask_value_interpolated() {
+		let mask = &GrayImage::from_fn(2, 2, |x, _y| {
+			if x == 0 {
+				Luma([0u8])
+			} else {
+				Luma([255u8])
+			}
+		});
+
+		assert_eq!(get_mask_value_interpolated(mask, 0.0, 0.0), 0.0);
+		assert_eq!(get_mask_value_interpolated(mask, 1.0, 1.0), 1.0);
+		assert_eq!(get_mask_value_interpolated(mask, 0.5, 0.0), 0.5);
+		assert_eq!(get_mask_value_interpolated(mask, 0.75, 1.0), 0.75);
+
+		// Outside the mask, fading to zero
+		assert_eq!(get_mask_value_interpolated(mask, 1.5, 0.0), 0.5);
+		assert_eq!(get_mask_value_interpolated(mask, 1.0, -0.5), 0.5);
+		assert_eq!(get_mask_value_interpolated(mask, 2.0, 0.0), 0.0);
+		assert_eq!(get_mask_value_interpolated(mask, -3.0, 5.0), 0.0);
+	}
 }
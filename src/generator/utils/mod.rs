@@ -1,12 +1,19 @@
+pub mod acceptance;
 pub mod benchmark;
 pub mod color;
+pub mod coloring;
+pub mod diff;
 pub mod files;
+pub mod fill;
 pub mod formatting;
 pub mod geom;
 pub mod image;
 pub mod numbers;
 pub mod parsing;
+pub mod patch;
 pub mod pixel;
+pub mod placement;
 pub mod random;
+pub mod regions;
 pub mod terminal;
 pub mod units;
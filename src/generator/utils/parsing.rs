@@ -1,9 +1,13 @@
 use std::convert::TryInto;
+use std::path::PathBuf;
 use std::str::FromStr;
 
 use color_processing::Color;
 
+use crate::generator::painter::PainterType;
 use crate::generator::utils::color::BlendingMode;
+use crate::generator::utils::fill::FillType;
+use crate::generator::utils::regions::RegionDefinition;
 use crate::generator::utils::units::{Margins, SizeUnit, WeightedValue};
 
 pub fn parse_color(src: &str) -> Result<(u8, u8, u8), &str> {
@@ -37,6 +41,24 @@ pub fn parse_float_pair(src: &str) -> Result<(f64, f64), &str> {
 	}
 }
 
+pub fn parse_integer(src: &str) -> Result<u32, &str> {
+	src.parse::<u32>().or(Err("Could not parse integer value"))
+}
+
+pub fn parse_integer_list(src: &str, divider: char) -> Result<Vec<u32>, &str> {
+	src.split(divider).collect::<Vec<&str>>().iter().map(|&e| parse_integer(e)).collect()
+}
+
+/// Parses "3", "3-8" into (3, 3), (3, 8)
+pub fn parse_integer_pair(src: &str) -> Result<(u32, u32), &str> {
+	let values = parse_integer_list(src, '-')?;
+	match values.len() {
+		1 => Ok((values[0], values[0])),
+		2 => Ok((values[0], values[1])),
+		_ => Err("Integer range must be 1-2"),
+	}
+}
+
 /// Parses "10%", "20.3" into 0.1, 20.3
 pub fn parse_scale(src: &str) -> Result<f64, &str> {
 	if src.ends_with("%") {
@@ -160,6 +182,32 @@ pub fn parse_weighted_float_pair(src: &str) -> Result<WeightedValue<(f64, f64)>,
 	}
 }
 
+/// Parses a file path with a weight (e.f. "brush.png@2", "images/brush.png") into a WeightedValue<>
+pub fn parse_weighted_path(src: &str) -> Result<WeightedValue<PathBuf>, &str> {
+	match parse_weight(src) {
+		Ok(("", _)) => Err("File path cannot be empty"),
+		Ok((src_value, weight)) => Ok(WeightedValue {
+			value: PathBuf::from(src_value),
+			weight,
+		}),
+		Err(err) => Err(err),
+	}
+}
+
+/// Parses an integer pair with a weight (e.f. "3-8@2", "5", "4-6") into a WeightedValue<>
+pub fn parse_weighted_integer_pair(src: &str) -> Result<WeightedValue<(u32, u32)>, &str> {
+	match parse_weight(src) {
+		Ok((src_value, weight)) => match parse_integer_pair(src_value) {
+			Ok(value) => Ok(WeightedValue {
+				value,
+				weight,
+			}),
+			Err(err) => Err(err),
+		},
+		Err(err) => Err(err),
+	}
+}
+
 /// Parses a blending mode with a weight (e.f. "normal", "screen@2") into a WeightedValue<>
 pub fn parse_weighted_blending_mode(src: &str) -> Result<WeightedValue<BlendingMode>, &str> {
 	match parse_weight(src) {
@@ -174,6 +222,46 @@ pub fn parse_weighted_blending_mode(src: &str) -> Result<WeightedValue<BlendingM
 	}
 }
 
+/// Parses a painter type with a weight (e.f. "circles", "rects@2") into a WeightedValue<>
+pub fn parse_weighted_painter_type(src: &str) -> Result<WeightedValue<PainterType>, &str> {
+	match parse_weight(src) {
+		Ok((src_value, weight)) => match PainterType::from_str(src_value) {
+			Ok(value) => Ok(WeightedValue {
+				value,
+				weight,
+			}),
+			Err(_) => Err("Cannot parse value variant for painter"),
+		},
+		Err(err) => Err(err),
+	}
+}
+
+/// Parses a fill type with a weight (e.f. "flat", "linear@2") into a WeightedValue<>
+pub fn parse_weighted_fill_type(src: &str) -> Result<WeightedValue<FillType>, &str> {
+	match parse_weight(src) {
+		Ok((src_value, weight)) => match FillType::from_str(src_value) {
+			Ok(value) => Ok(WeightedValue {
+				value,
+				weight,
+			}),
+			Err(_) => Err("Cannot parse value variant for fill type"),
+		},
+		Err(err) => Err(err),
+	}
+}
+
+/// Parses a region definition (e.g. "ff0000=--painter circles --painter-radius 1-4") into its label color
+/// and the list of painter options used for it
+pub fn parse_region(src: &str) -> Result<RegionDefinition, &str> {
+	match src.split_once('=') {
+		Some((color, options)) => {
+			let color = parse_color(color.trim())?;
+			Ok((color, options.split_whitespace().map(String::from).collect()))
+		}
+		None => Err("Region must be a color followed by '=' and its options"),
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -242,6 +330,25 @@ mod tests {
 		assert!(parse_float_pair("1-2-3").is_err());
 	}
 
+	#[test]
+	fn test_parse_integer_pair() {
+		// Singles
+		assert_eq!(parse_integer_pair("0"), Ok((0, 0)));
+		assert_eq!(parse_integer_pair("3"), Ok((3, 3)));
+		assert_eq!(parse_integer_pair("12"), Ok((12, 12)));
+
+		// Pairs
+		assert_eq!(parse_integer_pair("3-8"), Ok((3, 8)));
+		assert_eq!(parse_integer_pair("10-4"), Ok((10, 4)));
+
+		// Errors
+		assert!(parse_integer_pair("").is_err());
+		assert!(parse_integer_pair("foo").is_err());
+		assert!(parse_integer_pair("1.5").is_err());
+		assert!(parse_integer_pair("1-foo").is_err());
+		assert!(parse_integer_pair("1-2-3").is_err());
+	}
+
 	#[test]
 	fn test_parse_scale() {
 		assert_eq!(parse_scale("0"), Ok(0.0));
@@ -584,6 +691,62 @@ mod tests {
 		assert!(parse_weighted_float_pair("10%@a").is_err());
 	}
 
+	#[test]
+	fn test_parse_weighted_integer_pair() {
+		assert_eq!(
+			parse_weighted_integer_pair("3"),
+			Ok(WeightedValue {
+				value: (3, 3),
+				weight: 1.0
+			}),
+		);
+		assert_eq!(
+			parse_weighted_integer_pair("3-8@2"),
+			Ok(WeightedValue {
+				value: (3, 8),
+				weight: 2.0
+			}),
+		);
+		assert_eq!(
+			parse_weighted_integer_pair("5-6@0.5"),
+			Ok(WeightedValue {
+				value: (5, 6),
+				weight: 0.5
+			}),
+		);
+
+		// Errors
+		assert!(parse_weighted_integer_pair("").is_err());
+		assert!(parse_weighted_integer_pair("foo").is_err());
+		assert!(parse_weighted_integer_pair("3.5@1").is_err());
+		assert!(parse_weighted_integer_pair("3-8@1@2").is_err());
+		assert!(parse_weighted_integer_pair("3-8@a").is_err());
+	}
+
+	#[test]
+	fn test_parse_weighted_path() {
+		assert_eq!(
+			parse_weighted_path("brush.png"),
+			Ok(WeightedValue {
+				value: PathBuf::from("brush.png"),
+				weight: 1.0
+			}),
+		);
+		assert_eq!(
+			parse_weighted_path("images/brush.png@2.5"),
+			Ok(WeightedValue {
+				value: PathBuf::from("images/brush.png"),
+				weight: 2.5
+			}),
+		);
+
+		// Errors
+		assert!(parse_weighted_path("").is_err());
+		assert!(parse_weighted_path("@2").is_err());
+		assert!(parse_weighted_path("brush.png@a").is_err());
+		assert!(parse_weighted_path("brush.png@1@2").is_err());
+	}
+
 	#[test]
 	fn test_parse_weighted_blending_mode() {
 		assert_eq!(
@@ -632,4 +795,92 @@ mod tests {
 		assert!(parse_weighted_blending_mode("multiply@1_2").is_err());
 		assert!(parse_weighted_blending_mode("normal@a").is_err());
 	}
+
+	#[test]
+	fn test_parse_weighted_fill_type() {
+		assert_eq!(
+			parse_weighted_fill_type("flat"),
+			Ok(WeightedValue {
+				value: FillType::Flat,
+				weight: 1.0
+			}),
+		);
+		assert_eq!(
+			parse_weighted_fill_type("linear@2"),
+			Ok(WeightedValue {
+				value: FillType::Linear,
+				weight: 2.0
+			}),
+		);
+		assert_eq!(
+			parse_weighted_fill_type("radial@0.5"),
+			Ok(WeightedValue {
+				value: FillType::Radial,
+				weight: 0.5
+			}),
+		);
+
+		// Errors
+		assert!(parse_weighted_fill_type("").is_err());
+		assert!(parse_weighted_fill_type("foo").is_err());
+		assert!(parse_weighted_fill_type("linear@a").is_err());
+		assert!(parse_weighted_fill_type("linear@1@2").is_err());
+	}
+
+	#[test]
+	fn test_parse_weighted_painter_type() {
+		assert_eq!(
+			parse_weighted_painter_type("circles"),
+			Ok(WeightedValue {
+				value: PainterType::Circles,
+				weight: 1.0
+			}),
+		);
+		assert_eq!(
+			parse_weighted_painter_type("rects@2"),
+			Ok(WeightedValue {
+				value: PainterType::Rects,
+				weight: 2.0
+			}),
+		);
+		assert_eq!(
+			parse_weighted_painter_type("glyphs@0.25"),
+			Ok(WeightedValue {
+				value: PainterType::Glyphs,
+				weight: 0.25
+			}),
+		);
+
+		// Errors
+		assert!(parse_weighted_painter_type("").is_err());
+		assert!(parse_weighted_painter_type("circle").is_err());
+		assert!(parse_weighted_painter_type("rects@a").is_err());
+		assert!(parse_weighted_painter_type("rects@1@2").is_err());
+	}
+
+	#[test]
+	fn test_parse_region() {
+		assert_eq!(parse_region("ff0000="), Ok(((255, 0, 0), vec![])));
+		assert_eq!(
+			parse_region("#00ff00=--painter circles"),
+			Ok(((0, 255, 0), vec!["--painter".to_string(), "circles".to_string()]))
+		);
+		assert_eq!(
+			parse_region(" blue = --painter strokes  --painter-alpha 0.5 "),
+			Ok((
+				(0, 0, 255),
+				vec![
+					"--painter".to_string(),
+					"strokes".to_string(),
+					"--painter-alpha".to_string(),
+					"0.5".to_string()
+				]
+			))
+		);
+
+		// Errors
+		assert!(parse_region("").is_err());
+		assert!(parse_region("ff0000").is_err());
+		assert!(parse_region("foo=--painter circles").is_err());
+	}
 }
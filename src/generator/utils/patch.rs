@@ -0,0 +1,129 @@
+use image::{imageops, GrayImage, Pixel, Rgb, RgbImage};
+
+use crate::generator::utils::pixel::blend_linear;
+
+/// The result of a paint: the new pixels of a rectangular area of the canvas. Painters only touch the
+/// area they paint over, so this can be diffed and committed without copying the whole canvas.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Patch {
+	pub x: u32,
+	pub y: u32,
+	pub image: RgbImage,
+}
+
+impl Patch {
+	/// Creates a patch with a copy of the canvas area from (x1, y1) to (x2, y2), exclusive, clipped to the
+	/// canvas bounds.
+	pub fn from_canvas(canvas: &RgbImage, x1: u32, y1: u32, x2: u32, y2: u32) -> Patch {
+		let (width, height) = canvas.dimensions();
+		let x1 = x1.min(width);
+		let y1 = y1.min(height);
+		let x2 = x2.clamp(x1, width);
+		let y2 = y2.clamp(y1, height);
+		Patch {
+			x: x1,
+			y: y1,
+			image: imageops::crop_imm(canvas, x1, y1, x2 - x1, y2 - y1).to_image(),
+		}
+	}
+
+	/// Creates a patch that doesn't change anything.
+	pub fn empty() -> Patch {
+		Patch {
+			x: 0,
+			y: 0,
+			image: RgbImage::new(0, 0),
+		}
+	}
+
+	pub fn dimensions(&self) -> (u32, u32) {
+		self.image.dimensions()
+	}
+
+	/// Gets a pixel using canvas coordinates.
+	pub fn get_pixel(&self, x: u32, y: u32) -> &Rgb<u8> {
+		self.image.get_pixel(x - self.x, y - self.y)
+	}
+
+	/// Sets a pixel using canvas coordinates.
+	pub fn put_pixel(&mut self, x: u32, y: u32, pixel: Rgb<u8>) {
+		self.image.put_pixel(x - self.x, y - self.y, pixel);
+	}
+
+	/// Restricts the patch to a mask over the canvas: where the mask is black, the canvas is kept as is;
+	/// where it's white, the patch is kept; anything in between is a blend of the two.
+	pub fn mask(&mut self, canvas: &RgbImage, mask: &GrayImage) {
+		for (x, y, pixel) in self.image.enumerate_pixels_mut() {
+			let mask_value = mask.get_pixel(self.x + x, self.y + y)[0];
+			if mask_value < 255 {
+				let canvas_pixel = canvas.get_pixel(self.x + x, self.y + y);
+				*pixel =
+					Rgb(blend_linear(canvas_pixel.channels(), pixel.channels(), mask_value as f64 / 255.0));
+			}
+		}
+	}
+
+	/// Commits the patch into a canvas, in place.
+	pub fn apply(&self, canvas: &mut RgbImage) {
+		imageops::replace(canvas, &self.image, self.x, self.y);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_patch_from_canvas() {
+		let canvas = &RgbImage::from_fn(8, 8, |x, y| Rgb([x as u8, y as u8, 0u8]));
+
+		let patch = Patch::from_canvas(canvas, 2, 3, 5, 7);
+		assert_eq!((patch.x, patch.y), (2, 3));
+		assert_eq!(patch.dimensions(), (3, 4));
+		assert_eq!(patch.get_pixel(2, 3), &Rgb([2u8, 3u8, 0u8]));
+		assert_eq!(patch.get_pixel(4, 6), &Rgb([4u8, 6u8, 0u8]));
+
+		// Clipped
+		let patch = Patch::from_canvas(canvas, 6, 7, 10, 12);
+		assert_eq!((patch.x, patch.y), (6, 7));
+		assert_eq!(patch.dimensions(), (2, 1));
+
+		let patch = Patch::from_canvas(canvas, 9, 2, 12, 2);
+		assert_eq!((patch.x, patch.y), (8, 2));
+		assert_eq!(patch.dimensions(), (0, 0));
+	}
+
+	#[test]
+	fn test_patch_apply() {
+		let mut canvas = RgbImage::from_fn(4, 4, |_x, _y| Rgb([0u8, 0u8, 0u8]));
+
+		let mut patch = Patch::from_canvas(&canvas, 1, 1, 3, 2);
+		patch.put_pixel(2, 1, Rgb([255u8, 0u8, 0u8]));
+		patch.apply(&mut canvas);
+		assert_eq!(canvas.get_pixel(2, 1), &Rgb([255u8, 0u8, 0u8]));
+		assert_eq!(canvas.get_pixel(1, 1), &Rgb([0u8, 0u8, 0u8]));
+		assert_eq!(canvas.get_pixel(2, 2), &Rgb([0u8, 0u8, 0u8]));
+
+		Patch::empty().apply(&mut canvas);
+		assert_eq!(canvas.get_pixel(2, 1), &Rgb([255u8, 0u8, 0u8]));
+	}
+
+	#[test]
+	fn test_patch_mask() {
+		let canvas = &RgbImage::from_fn(4, 4, |_x, _y| Rgb([0u8, 0u8, 0u8]));
+		let mask = &GrayImage::from_fn(4, 4, |x, _y| match x {
+			0 => image::Luma([0u8]),
+			1 => image::Luma([128u8]),
+			_ => image::Luma([255u8]),
+		});
+
+		let mut patch = Patch::from_canvas(canvas, 0, 1, 3, 2);
+		for x in 0..3 {
+			patch.put_pixel(x, 1, Rgb([255u8, 100u8, 0u8]));
+		}
+		patch.mask(canvas, mask);
+		assert_eq!(patch.get_pixel(0, 1), &Rgb([0u8, 0u8, 0u8]));
+		assert_eq!(patch.get_pixel(1, 1), &Rgb([128u8, 50u8, 0u8]));
+		assert_eq!(patch.get_pixel(2, 1), &Rgb([255u8, 100u8, 0u8]));
+	}
+}
@@ -1,5 +1,14 @@
+use std::sync::OnceLock;
+
 use crate::generator::utils::color::BlendingMode;
 
+// D65 reference white
+const LAB_WHITE_X: f64 = 0.95047;
+const LAB_WHITE_Y: f64 = 1.0;
+const LAB_WHITE_Z: f64 = 1.08883;
+const LAB_EPSILON: f64 = 216.0 / 24389.0;
+const LAB_KAPPA: f64 = 24389.0 / 27.0;
+
 #[inline(always)]
 pub fn blend(bottom: &[u8], top: &[u8], opacity: f64, blending_mode: &BlendingMode) -> [u8; 3] {
 	if opacity == 0.0 {
@@ -50,6 +59,143 @@ fn color_matrix_channel(rgb: [f64; 3], rgb_mul: [f64; 3], offset: f64) -> u8 {
 	result.round().max(0.0).min(255.0) as u8
 }
 
+/// Converts an sRGB pixel to CIELAB, using a D65 reference white.
+#[inline(always)]
+pub fn to_lab(pixel: &[u8]) -> [f64; 3] {
+	let linear = get_srgb_to_linear_table();
+	let (r, g, b) = (linear[pixel[0] as usize], linear[pixel[1] as usize], linear[pixel[2] as usize]);
+	let x = (0.4124564 * r + 0.3575761 * g + 0.1804375 * b) / LAB_WHITE_X;
+	let y = (0.2126729 * r + 0.7151522 * g + 0.0721750 * b) / LAB_WHITE_Y;
+	let z = (0.0193339 * r + 0.1191920 * g + 0.9503041 * b) / LAB_WHITE_Z;
+	let (fx, fy, fz) = (lab_f(x), lab_f(y), lab_f(z));
+	[116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+/// CIE76 color difference: the euclidean distance between two CIELAB colors.
+#[inline(always)]
+pub fn delta_e_76(lab1: &[f64; 3], lab2: &[f64; 3]) -> f64 {
+	let dl = lab1[0] - lab2[0];
+	let da = lab1[1] - lab2[1];
+	let db = lab1[2] - lab2[2];
+	(dl * dl + da * da + db * db).sqrt()
+}
+
+/// CIE94 color difference, with graphic arts weights. `lab1` is the reference color.
+#[inline(always)]
+pub fn delta_e_94(lab1: &[f64; 3], lab2: &[f64; 3]) -> f64 {
+	let dl = lab1[0] - lab2[0];
+	let c1 = lab1[1].hypot(lab1[2]);
+	let c2 = lab2[1].hypot(lab2[2]);
+	let dc = c1 - c2;
+	let da = lab1[1] - lab2[1];
+	let db = lab1[2] - lab2[2];
+	let dh_squared = (da * da + db * db - dc * dc).max(0.0);
+	let sc = 1.0 + 0.045 * c1;
+	let sh = 1.0 + 0.015 * c1;
+	(dl * dl + (dc / sc).powi(2) + dh_squared / (sh * sh)).sqrt()
+}
+
+/// CIEDE2000 color difference.
+pub fn delta_e_2000(lab1: &[f64; 3], lab2: &[f64; 3]) -> f64 {
+	let pow_25_7 = 25.0f64.powi(7);
+	let (l1, a1, b1) = (lab1[0], lab1[1], lab1[2]);
+	let (l2, a2, b2) = (lab2[0], lab2[1], lab2[2]);
+
+	// Adjust the a axis, so neutral colors are compared more evenly
+	let c_avg_7 = ((a1.hypot(b1) + a2.hypot(b2)) / 2.0).powi(7);
+	let g = 0.5 * (1.0 - (c_avg_7 / (c_avg_7 + pow_25_7)).sqrt());
+	let a1p = (1.0 + g) * a1;
+	let a2p = (1.0 + g) * a2;
+	let c1p = a1p.hypot(b1);
+	let c2p = a2p.hypot(b2);
+	let h1p = lab_hue(a1p, b1);
+	let h2p = lab_hue(a2p, b2);
+
+	// Differences in lightness, chroma, and hue
+	let dlp = l2 - l1;
+	let dcp = c2p - c1p;
+	let dhp = if c1p * c2p == 0.0 {
+		0.0
+	} else if h2p - h1p > 180.0 {
+		h2p - h1p - 360.0
+	} else if h2p - h1p < -180.0 {
+		h2p - h1p + 360.0
+	} else {
+		h2p - h1p
+	};
+	let dhp_big = 2.0 * (c1p * c2p).sqrt() * (dhp / 2.0).to_radians().sin();
+
+	// Averages
+	let lp_avg = (l1 + l2) / 2.0;
+	let cp_avg = (c1p + c2p) / 2.0;
+	let hp_avg = if c1p * c2p == 0.0 {
+		h1p + h2p
+	} else if (h1p - h2p).abs() <= 180.0 {
+		(h1p + h2p) / 2.0
+	} else if h1p + h2p < 360.0 {
+		(h1p + h2p + 360.0) / 2.0
+	} else {
+		(h1p + h2p - 360.0) / 2.0
+	};
+
+	// Weighting functions
+	let t = 1.0 - 0.17 * (hp_avg - 30.0).to_radians().cos()
+		+ 0.24 * (2.0 * hp_avg).to_radians().cos()
+		+ 0.32 * (3.0 * hp_avg + 6.0).to_radians().cos()
+		- 0.20 * (4.0 * hp_avg - 63.0).to_radians().cos();
+	let d_theta = 30.0 * (-((hp_avg - 275.0) / 25.0).powi(2)).exp();
+	let cp_avg_7 = cp_avg.powi(7);
+	let rc = 2.0 * (cp_avg_7 / (cp_avg_7 + pow_25_7)).sqrt();
+	let lp_offset_squared = (lp_avg - 50.0).powi(2);
+	let sl = 1.0 + 0.015 * lp_offset_squared / (20.0 + lp_offset_squared).sqrt();
+	let sc = 1.0 + 0.045 * cp_avg;
+	let sh = 1.0 + 0.015 * cp_avg * t;
+	let rt = -(2.0 * d_theta).to_radians().sin() * rc;
+
+	((dlp / sl).powi(2) + (dcp / sc).powi(2) + (dhp_big / sh).powi(2) + rt * (dcp / sc) * (dhp_big / sh))
+		.sqrt()
+}
+
+fn get_srgb_to_linear_table() -> &'static [f64; 256] {
+	static TABLE: OnceLock<[f64; 256]> = OnceLock::new();
+	TABLE.get_or_init(|| {
+		let mut table = [0.0; 256];
+		for (i, value) in table.iter_mut().enumerate() {
+			let c = i as f64 / 255.0;
+			*value = if c <= 0.04045 {
+				c / 12.92
+			} else {
+				((c + 0.055) / 1.055).powf(2.4)
+			};
+		}
+		table
+	})
+}
+
+#[inline(always)]
+fn lab_f(t: f64) -> f64 {
+	if t > LAB_EPSILON {
+		t.cbrt()
+	} else {
+		(LAB_KAPPA * t + 16.0) / 116.0
+	}
+}
+
+/// Hue angle of a CIELAB color, in degrees (`0.0`..`360.0`).
+#[inline(always)]
+fn lab_hue(a: f64, b: f64) -> f64 {
+	if a == 0.0 && b == 0.0 {
+		0.0
+	} else {
+		let hue = b.atan2(a).to_degrees();
+		if hue < 0.0 {
+			hue + 360.0
+		} else {
+			hue
+		}
+	}
+}
+
 #[inline(always)]
 fn channel_f64_to_u8(color: f64) -> u8 {
 	(color * 255.0).round() as u8
@@ -296,4 +442,49 @@ mod tests {
 		assert_eq!(channel_f64_to_u8(0.75), 191);
 		assert_eq!(channel_f64_to_u8(1.0), 255);
 	}
+
+	fn assert_lab_eq(lab: [f64; 3], expected: [f64; 3]) {
+		for i in 0..3 {
+			assert!((lab[i] - expected[i]).abs() < 0.01, "{:?} != {:?}", lab, expected);
+		}
+	}
+
+	#[test]
+	fn test_to_lab() {
+		assert_lab_eq(to_lab(&[0, 0, 0]), [0.0, 0.0, 0.0]);
+		assert_lab_eq(to_lab(&[255, 255, 255]), [100.0, 0.0, 0.0]);
+		assert_lab_eq(to_lab(&[255, 0, 0]), [53.24, 80.09, 67.20]);
+		assert_lab_eq(to_lab(&[0, 255, 0]), [87.73, -86.18, 83.18]);
+		assert_lab_eq(to_lab(&[0, 0, 255]), [32.30, 79.19, -107.86]);
+	}
+
+	#[test]
+	fn test_delta_e_76() {
+		assert_eq!(delta_e_76(&[50.0, 10.0, 10.0], &[50.0, 10.0, 10.0]), 0.0);
+		assert_eq!(delta_e_76(&[0.0, 0.0, 0.0], &[100.0, 0.0, 0.0]), 100.0);
+		assert_eq!(delta_e_76(&[50.0, 0.0, 0.0], &[50.0, 3.0, 4.0]), 5.0);
+	}
+
+	#[test]
+	fn test_delta_e_94() {
+		assert_eq!(delta_e_94(&[50.0, 10.0, 10.0], &[50.0, 10.0, 10.0]), 0.0);
+		assert_eq!(delta_e_94(&[0.0, 0.0, 0.0], &[100.0, 0.0, 0.0]), 100.0);
+		assert!((delta_e_94(&[50.0, 2.6772, -79.7751], &[50.0, 0.0, -82.7485]) - 1.3950).abs() < 0.0001);
+	}
+
+	#[test]
+	fn test_delta_e_2000() {
+		// Reference values from Sharma, Wu, and Dalal's CIEDE2000 test data
+		let assert_delta_e = |lab1: [f64; 3], lab2: [f64; 3], expected: f64| {
+			assert!((delta_e_2000(&lab1, &lab2) - expected).abs() < 0.0001);
+			assert!((delta_e_2000(&lab2, &lab1) - expected).abs() < 0.0001);
+		};
+		assert_delta_e([50.0, 2.6772, -79.7751], [50.0, 0.0, -82.7485], 2.0425);
+		assert_delta_e([50.0, 3.1571, -77.2803], [50.0, 0.0, -82.7485], 2.8615);
+		assert_delta_e([50.0, 0.0, 0.0], [50.0, -1.0, 2.0], 2.3669);
+		assert_delta_e([50.0, 2.49, -0.001], [50.0, -2.49, 0.0009], 7.1792);
+		assert_delta_e([50.0, 2.5, 0.0], [73.0, 25.0, -18.0], 27.1492);
+		assert_delta_e([50.0, 2.5, 0.0], [50.0, 3.1736, 0.5854], 1.0);
+		assert_eq!(delta_e_2000(&[50.0, 10.0, 10.0], &[50.0, 10.0, 10.0]), 0.0);
+	}
 }
@@ -0,0 +1,153 @@
+use strum_macros::{Display, EnumString};
+
+use crate::generator::utils::random::rng::Rng;
+
+/// Where painters place new elements.
+#[derive(Clone, Copy, Debug, Default, Display, EnumString, PartialEq)]
+pub enum Placement {
+	#[default]
+	#[strum(serialize = "uniform")]
+	Uniform,
+	#[strum(serialize = "error")]
+	Error,
+}
+
+/// Keeps the running sums of the difference of each row of pixels, so random positions can be picked in
+/// proportion to how different their pixels currently are from the target.
+#[derive(Clone, Debug)]
+pub struct ErrorMap {
+	width: u32,
+	height: u32,
+	row_sums: Vec<f32>,
+}
+
+impl ErrorMap {
+	pub fn new(width: u32, height: u32, errors: &[f32]) -> ErrorMap {
+		let mut error_map = ErrorMap {
+			width,
+			height,
+			row_sums: vec![0.0; ((width + 1) * height) as usize],
+		};
+		error_map.update(errors, 0, height);
+		error_map
+	}
+
+	/// Updates the sums of the rows from y1 to y2 (exclusive), after their differences have changed.
+	pub fn update(&mut self, errors: &[f32], y1: u32, y2: u32) {
+		let stride = (self.width + 1) as usize;
+		for y in y1..y2.min(self.height) {
+			let row_errors = &errors[(y * self.width) as usize..((y + 1) * self.width) as usize];
+			let row_sums = &mut self.row_sums[y as usize * stride..(y as usize + 1) * stride];
+			let mut sum = 0.0f32;
+			for (x, error) in row_errors.iter().enumerate() {
+				sum += error.max(0.0);
+				row_sums[x + 1] = sum;
+			}
+		}
+	}
+
+	/// Picks a random position inside an area (both ends inclusive), in proportion to the difference of
+	/// its pixels. Returns `None` if the area has no pixels with any difference.
+	pub fn get_random_position(
+		&self,
+		rng: &mut Rng,
+		x_range: (f64, f64),
+		y_range: (f64, f64),
+	) -> Option<(f64, f64)> {
+		if x_range.0 > x_range.1 || y_range.0 > y_range.1 {
+			return None;
+		}
+
+		// Pixels touching the area
+		let x1 = x_range.0.floor().max(0.0).min(self.width as f64) as usize;
+		let y1 = y_range.0.floor().max(0.0).min(self.height as f64) as usize;
+		let x2 = (x_range.1.floor() + 1.0).max(0.0).min(self.width as f64) as usize;
+		let y2 = (y_range.1.floor() + 1.0).max(0.0).min(self.height as f64) as usize;
+		if x1 >= x2 || y1 >= y2 {
+			return None;
+		}
+
+		let stride = (self.width + 1) as usize;
+		let row_sum = |y: usize| (self.row_sums[y * stride + x2] - self.row_sums[y * stride + x1]).max(0.0);
+		let total: f64 = (y1..y2).map(|y| row_sum(y) as f64).sum();
+		if total <= 0.0 {
+			return None;
+		}
+
+		// Pick a row, then a pixel inside of it
+		let mut position = rng.next_f64() * total;
+		let y = (y1..y2)
+			.find(|&y| {
+				position -= row_sum(y) as f64;
+				position < 0.0
+			})
+			.unwrap_or(y2 - 1);
+		let row = &self.row_sums[y * stride..(y + 1) * stride];
+		let position = row[x1] + rng.next_f64() as f32 * (row[x2] - row[x1]);
+		let x = (x1 + row[x1 + 1..=x2].partition_point(|&sum| sum <= position)).min(x2 - 1);
+
+		Some((
+			(x as f64 + rng.next_f64()).clamp(x_range.0, x_range.1),
+			(y as f64 + rng.next_f64()).clamp(y_range.0, y_range.1),
+		))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_error_map_get_random_position() {
+		let mut rng = Rng::from_seed(1);
+
+		// Only one pixel has any difference
+		let mut errors = vec![0.0f32; 16];
+		errors[9] = 0.5;
+		let mut error_map = ErrorMap::new(4, 4, &errors);
+		for _ in 0..100 {
+			let (x, y) = error_map.get_random_position(&mut rng, (0.0, 4.0), (0.0, 4.0)).unwrap();
+			assert!((1.0..2.0).contains(&x));
+			assert!((2.0..3.0).contains(&y));
+		}
+
+		// Positions are kept inside the area
+		let (x, y) = error_map.get_random_position(&mut rng, (1.5, 1.6), (2.0, 4.0)).unwrap();
+		assert!((1.5..=1.6).contains(&x));
+		assert!((2.0..3.0).contains(&y));
+
+		// Areas without any difference
+		assert_eq!(error_map.get_random_position(&mut rng, (2.0, 4.0), (0.0, 4.0)), None);
+		assert_eq!(error_map.get_random_position(&mut rng, (3.0, 1.0), (0.0, 4.0)), None);
+		assert_eq!(error_map.get_random_position(&mut rng, (5.0, 8.0), (0.0, 4.0)), None);
+
+		// Rows are updated
+		errors[9] = 0.0;
+		errors[3] = 1.0;
+		error_map.update(&errors, 0, 3);
+		let (x, y) = error_map.get_random_position(&mut rng, (0.0, 4.0), (0.0, 4.0)).unwrap();
+		assert!((3.0..4.0).contains(&x));
+		assert!((0.0..1.0).contains(&y));
+	}
+
+	#[test]
+	fn test_error_map_distribution() {
+		let mut rng = Rng::from_seed(1);
+
+		// The right half has three times as much difference as the left half
+		let errors: Vec<f32> = (0..64)
+			.map(|index| {
+				if index % 8 < 4 {
+					0.25
+				} else {
+					0.75
+				}
+			})
+			.collect();
+		let error_map = ErrorMap::new(8, 8, &errors);
+		let num_right = (0..1000)
+			.filter(|_| error_map.get_random_position(&mut rng, (0.0, 8.0), (0.0, 8.0)).unwrap().0 >= 4.0)
+			.count();
+		assert!((700..800).contains(&num_right));
+	}
+}
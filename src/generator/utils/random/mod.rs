@@ -1,5 +1,6 @@
 use std::f64::consts::PI;
 
+use crate::generator::utils::placement::ErrorMap;
 use crate::generator::utils::units::{SizeUnit, WeightedValue};
 use rng::Rng;
 
@@ -14,11 +15,32 @@ pub fn get_rng(seed: u32, iteration: u32) -> Rng {
 	Rng::from_seed(seed.wrapping_add(Rng::from_seed(iteration).next()))
 }
 
+/// Creates a random number generator for a single pick on each iteration, like picking a painter. Unlike
+/// with `get_rng()`, the first numbers of nearby iterations aren't similar to each other.
+pub fn get_pick_rng(seed: u32, iteration: u32) -> Rng {
+	// Multiplying by a large odd number spreads the difference between nearby iterations to all bits
+	get_rng(seed, iteration.wrapping_mul(0x9e37_79b9))
+}
+
 #[inline(always)]
 pub fn get_random_range(rng: &mut Rng, min: f64, pseudo_max: f64) -> f64 {
 	rng.next_f64_range(min, pseudo_max)
 }
 
+/// Picks a random position for a new element. Without an error map, this is uniform across the area;
+/// otherwise, it's in proportion to the current difference of each pixel from the target.
+pub fn get_random_position(
+	rng: &mut Rng,
+	error_map: Option<&ErrorMap>,
+	x_range: (f64, f64),
+	y_range: (f64, f64),
+) -> (f64, f64) {
+	match error_map.and_then(|error_map| error_map.get_random_position(rng, x_range, y_range)) {
+		Some(position) => position,
+		None => (get_random_range(rng, x_range.0, x_range.1), get_random_range(rng, y_range.0, y_range.1)),
+	}
+}
+
 #[inline(always)]
 pub fn get_random_range_bias(rng: &mut Rng, min: f64, max: f64, bias: f64) -> f64 {
 	if min == max {
@@ -46,17 +68,40 @@ pub fn get_random_size_range_bias(
 }
 
 pub fn get_random_entry_weighted<'a, T>(rng: &mut Rng, entries: &'a Vec<WeightedValue<T>>) -> &'a T {
+	&entries[get_random_index_weighted(rng, entries)].value
+}
+
+/// Picks a weighted entry like `get_random_entry_weighted()`, but doesn't use the rng when there's only one
+/// entry. Options added after the first painters are picked this way, so that their default values don't
+/// change the images previously generated with a given seed.
+pub fn get_random_entry_weighted_lazy<'a, T>(rng: &mut Rng, entries: &'a Vec<WeightedValue<T>>) -> &'a T {
+	if entries.len() == 1 {
+		&entries[0].value
+	} else {
+		get_random_entry_weighted(rng, entries)
+	}
+}
+
+/// Picks a weighted entry, and returns its position in the list. Entries with no weight are never picked.
+pub fn get_random_index_weighted<T>(rng: &mut Rng, entries: &Vec<WeightedValue<T>>) -> usize {
 	let total_weight = entries.iter().map(|r| r.weight).sum();
 	let desired_position = get_random_range(rng, 0.0, total_weight);
 	let mut acc = 0.0f64;
-	&entries
+	entries
 		.iter()
-		.find(|&r| {
+		.position(|r| {
 			acc += r.weight;
-			acc >= desired_position
+			r.weight > 0.0 && acc >= desired_position
 		})
 		.expect("finding weighted random value")
-		.value
+}
+
+/// Picks a weighted integer range, and returns a value from it (both ends inclusive).
+pub fn get_random_integer_ranges_weighted(rng: &mut Rng, ranges: &Vec<WeightedValue<(u32, u32)>>) -> u32 {
+	let range = get_random_entry_weighted(rng, ranges);
+	let min = range.0.min(range.1);
+	let max = range.0.max(range.1);
+	rng.next_u32_range(min, max + 1).min(max)
 }
 
 pub fn get_random_ranges_bias_weighted(
@@ -68,6 +113,17 @@ pub fn get_random_ranges_bias_weighted(
 	get_random_range_bias(rng, range.0, range.1, bias)
 }
 
+/// Picks a weighted range and returns a value from it, like `get_random_ranges_bias_weighted()`, but
+/// without using the rng when there's a single range with the same start and end.
+pub fn get_random_ranges_bias_weighted_lazy(
+	rng: &mut Rng,
+	ranges: &Vec<WeightedValue<(f64, f64)>>,
+	bias: f64,
+) -> f64 {
+	let range = get_random_entry_weighted_lazy(rng, ranges);
+	get_random_range_bias(rng, range.0, range.1, bias)
+}
+
 pub fn get_random_size_ranges_bias_weighted(
 	rng: &mut Rng,
 	ranges: &Vec<WeightedValue<(SizeUnit, SizeUnit)>>,
@@ -112,3 +168,38 @@ pub fn get_noise_value(noise: [f64; 256], position: f64) -> f64 {
 pub fn get_random_color(rng: &mut Rng) -> [u8; 3] {
 	[rng.next_u32_range(0, 256) as u8, rng.next_u32_range(0, 256) as u8, rng.next_u32_range(0, 256) as u8]
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_get_random_index_weighted_skips_zero_weights() {
+		let entries = vec![
+			WeightedValue {
+				value: 'a',
+				weight: 0.0,
+			},
+			WeightedValue {
+				value: 'b',
+				weight: 1.0,
+			},
+			WeightedValue {
+				value: 'c',
+				weight: 0.0,
+			},
+			WeightedValue {
+				value: 'd',
+				weight: 2.0,
+			},
+		];
+		let mut counts = [0u32; 4];
+		for iteration in 0..1000 {
+			counts[get_random_index_weighted(&mut get_pick_rng(1, iteration), &entries)] += 1;
+		}
+		assert_eq!(counts[0], 0);
+		assert_eq!(counts[2], 0);
+		assert!(counts[1] > 0);
+		assert!(counts[3] > counts[1]);
+	}
+}
@@ -0,0 +1,151 @@
+use image::RgbImage;
+use strum_macros::{Display, EnumString};
+
+use crate::generator::utils::units::Rectangle;
+
+/// A region label color, and the painter options used for it.
+pub type RegionDefinition = ((u8, u8, u8), Vec<String>);
+
+/// How likely each region of a label map is to be picked for a new paint.
+#[derive(Clone, Copy, Debug, Default, Display, EnumString, PartialEq)]
+pub enum RegionWeight {
+	#[default]
+	#[strum(serialize = "area")]
+	Area,
+	#[strum(serialize = "error")]
+	Error,
+}
+
+/// Finds the label of each pixel of a label map: `0` for pixels of no specific region, or the position
+/// of their color in `colors`, plus one.
+pub fn get_labels(label_map: &RgbImage, colors: &[(u8, u8, u8)]) -> Vec<u16> {
+	label_map
+		.pixels()
+		.map(|pixel| {
+			colors
+				.iter()
+				.position(|&(r, g, b)| pixel.0 == [r, g, b])
+				.map_or(0, |position| position as u16 + 1)
+		})
+		.collect()
+}
+
+/// Finds the number of pixels with each label.
+pub fn get_label_areas(labels: &[u16], num_labels: usize) -> Vec<u32> {
+	let mut areas = vec![0u32; num_labels];
+	for &label in labels {
+		areas[label as usize] += 1;
+	}
+	areas
+}
+
+/// Finds the bounding box of the pixels with each label; labels without any pixels have an empty box.
+pub fn get_label_bounds(labels: &[u16], width: u32, num_labels: usize) -> Vec<Rectangle<u32>> {
+	let mut bounds = vec![(u32::MAX, u32::MAX, 0u32, 0u32); num_labels];
+	for (index, &label) in labels.iter().enumerate() {
+		let x = index as u32 % width;
+		let y = index as u32 / width;
+		let (x1, y1, x2, y2) = &mut bounds[label as usize];
+		*x1 = (*x1).min(x);
+		*y1 = (*y1).min(y);
+		*x2 = (*x2).max(x + 1);
+		*y2 = (*y2).max(y + 1);
+	}
+	bounds
+		.into_iter()
+		.map(|(x1, y1, x2, y2)| {
+			if x1 > x2 {
+				Rectangle {
+					x: 0,
+					y: 0,
+					width: 0,
+					height: 0,
+				}
+			} else {
+				Rectangle {
+					x: x1,
+					y: y1,
+					width: x2 - x1,
+					height: y2 - y1,
+				}
+			}
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use image::Rgb;
+
+	use super::*;
+
+	fn get_test_label_map() -> RgbImage {
+		// Red square in the middle, a blue pixel at the bottom right, and white everywhere else
+		RgbImage::from_fn(4, 4, |x, y| {
+			if (1..3).contains(&x) && (1..3).contains(&y) {
+				Rgb([255u8, 0u8, 0u8])
+			} else if x == 3 && y == 3 {
+				Rgb([0u8, 0u8, 255u8])
+			} else {
+				Rgb([255u8, 255u8, 255u8])
+			}
+		})
+	}
+
+	#[test]
+	fn test_get_labels() {
+		let labels = get_labels(&get_test_label_map(), &[(255, 0, 0), (0, 0, 255), (0, 255, 0)]);
+		assert_eq!(labels, vec![0, 0, 0, 0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 0, 0, 2]);
+
+		let labels = get_labels(&get_test_label_map(), &[]);
+		assert_eq!(labels, vec![0; 16]);
+	}
+
+	#[test]
+	fn test_get_label_areas() {
+		let labels = get_labels(&get_test_label_map(), &[(255, 0, 0), (0, 0, 255), (0, 255, 0)]);
+		assert_eq!(get_label_areas(&labels, 4), vec![11, 4, 1, 0]);
+	}
+
+	#[test]
+	fn test_get_label_bounds() {
+		let labels = get_labels(&get_test_label_map(), &[(255, 0, 0), (0, 0, 255), (0, 255, 0)]);
+		let bounds = get_label_bounds(&labels, 4, 4);
+		assert_eq!(
+			bounds[0],
+			Rectangle {
+				x: 0,
+				y: 0,
+				width: 4,
+				height: 4
+			}
+		);
+		assert_eq!(
+			bounds[1],
+			Rectangle {
+				x: 1,
+				y: 1,
+				width: 2,
+				height: 2
+			}
+		);
+		assert_eq!(
+			bounds[2],
+			Rectangle {
+				x: 3,
+				y: 3,
+				width: 1,
+				height: 1
+			}
+		);
+		assert_eq!(
+			bounds[3],
+			Rectangle {
+				x: 0,
+				y: 0,
+				width: 0,
+				height: 0
+			}
+		);
+	}
+}
@@ -0,0 +1,254 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{mpsc, Arc};
+use std::thread;
+
+use image::{GrayImage, RgbImage};
+
+use crate::generator::painter::shape::Shape;
+use crate::generator::utils::acceptance::Acceptor;
+use crate::generator::utils::diff::DiffMap;
+use crate::generator::utils::patch::Patch;
+use crate::generator::utils::placement::ErrorMap;
+use crate::generator::{set_optimal_color, Painters, ProcessResult};
+
+/// A candidate to be painted: its index, its iteration, and the indices of its region and painter.
+pub type Candidate = (usize, u32, usize, usize);
+
+/// The best candidate of a try: its shape, patch, diff value, and the indices of the candidate, region,
+/// and painter used.
+pub type BestCandidate = (Box<Shape>, Patch, f64, usize, usize, usize);
+
+/// The candidates a thread paints in a try, and the state they're all painted over.
+struct Job {
+	current: Arc<RgbImage>,
+	diff_map: Arc<DiffMap>,
+	error_map: Arc<Option<ErrorMap>>,
+	curr_diff: f64,
+	acceptor: Acceptor,
+	candidates: Vec<Candidate>,
+}
+
+/// A pool of long-lived threads painting candidates. Threads are only created once per process, and every
+/// try just sends them the candidates to paint along with the shared current image, rather than a copy of
+/// it.
+pub struct CandidateWorkers {
+	job_senders: Vec<mpsc::Sender<Job>>,
+	result_receiver: mpsc::Receiver<ProcessResult>,
+	handles: Vec<thread::JoinHandle<()>>,
+}
+
+impl CandidateWorkers {
+	pub fn new(
+		threads: usize,
+		regions: &Arc<Vec<Painters>>,
+		target: &Arc<RgbImage>,
+		paint_mask: &Arc<Option<GrayImage>>,
+		importance_map: &Arc<Option<GrayImage>>,
+		coverage_canvas: &Arc<Option<RgbImage>>,
+	) -> CandidateWorkers {
+		let (result_sender, result_receiver) = mpsc::channel();
+		let mut job_senders = Vec::with_capacity(threads);
+		let mut handles = Vec::with_capacity(threads);
+
+		for _ in 0..threads {
+			let (job_sender, job_receiver) = mpsc::channel::<Job>();
+			let result_sender = mpsc::Sender::clone(&result_sender);
+			let regions = Arc::clone(regions);
+			let target = Arc::clone(target);
+			let paint_mask = Arc::clone(paint_mask);
+			let importance_map = Arc::clone(importance_map);
+			let coverage_canvas = Arc::clone(coverage_canvas);
+
+			handles.push(thread::spawn(move || {
+				for job in job_receiver {
+					// A panicking painter would never report back, and would hang the try, so its panic is
+					// reported as an error instead
+					let painted = panic::catch_unwind(AssertUnwindSafe(|| {
+						for &(candidate, iteration, region_index, painter_index) in &job.candidates {
+							let painter = &regions[region_index][painter_index].value;
+							let result = match painter
+								.get_shape(&job.current, iteration, &target, job.error_map.as_ref().as_ref())
+								.and_then(|mut new_shape| {
+									if let Some(coverage_canvas) = coverage_canvas.as_ref() {
+										set_optimal_color(
+											painter.as_ref(),
+											&mut new_shape,
+											coverage_canvas,
+											&job.current,
+											&target,
+											paint_mask.as_ref().as_ref(),
+											importance_map.as_ref().as_ref(),
+										);
+									}
+									painter
+										.paint_shape(&job.current, &new_shape)
+										.map(|patch| (new_shape, patch))
+								}) {
+								Ok((new_shape, mut new_candidate)) => {
+									if let Some(paint_mask) = paint_mask.as_ref() {
+										new_candidate.mask(&job.current, paint_mask);
+									}
+									let new_diff = job.diff_map.get_patch_diff(&new_candidate, &target);
+
+									// Only report candidates that could actually be accepted, to minimize the
+									// back-and-forth of data. To be fair, however, this doesn't seem to to do
+									// much in terms of performance.
+									if job.acceptor.is_candidate(job.curr_diff, new_diff) {
+										ProcessResult::Ok(
+											new_candidate,
+											Box::new(new_shape),
+											new_diff,
+											candidate,
+											region_index,
+											painter_index,
+										)
+									} else {
+										ProcessResult::Ignore
+									}
+								}
+								Err(err) => ProcessResult::Error(err.to_owned()),
+							};
+							result_sender.send(result).unwrap();
+						}
+					}));
+					if painted.is_err() {
+						result_sender
+							.send(ProcessResult::Error("Candidate thread panicked".to_owned()))
+							.unwrap();
+					}
+
+					// The shared state needs to be released before reporting, so it can be updated
+					drop(job);
+					result_sender.send(ProcessResult::Done).unwrap();
+				}
+			}));
+			job_senders.push(job_sender);
+		}
+
+		CandidateWorkers {
+			job_senders,
+			result_receiver,
+			handles,
+		}
+	}
+
+	/// Paints all candidates of a try, and returns the best one that could be accepted, if any. Candidates
+	/// are spread over the threads, but results arrive in any order, so ties are broken by the index of the
+	/// candidate. Once this returns, threads don't hold any reference to the shared state anymore.
+	pub fn paint(
+		&self,
+		current: &Arc<RgbImage>,
+		diff_map: &Arc<DiffMap>,
+		error_map: &Arc<Option<ErrorMap>>,
+		curr_diff: f64,
+		acceptor: &Acceptor,
+		candidates: Vec<Candidate>,
+	) -> Option<BestCandidate> {
+		let num_threads = self.job_senders.len();
+		for (thread_index, job_sender) in self.job_senders.iter().enumerate() {
+			job_sender
+				.send(Job {
+					current: Arc::clone(current),
+					diff_map: Arc::clone(diff_map),
+					error_map: Arc::clone(error_map),
+					curr_diff,
+					acceptor: acceptor.clone(),
+					candidates: candidates.iter().skip(thread_index).step_by(num_threads).copied().collect(),
+				})
+				.expect("sending candidates to thread");
+		}
+
+		let mut best_candidate: Option<BestCandidate> = None;
+		let mut num_done = 0;
+		while num_done < num_threads {
+			match self.result_receiver.recv().expect("receiving candidate from thread") {
+				ProcessResult::Ok(
+					new_candidate,
+					new_shape,
+					new_diff,
+					candidate,
+					region_index,
+					painter_index,
+				) => {
+					if best_candidate.as_ref().is_none_or(|best| (new_diff, candidate) < (best.2, best.3)) {
+						best_candidate = Some((
+							new_shape,
+							new_candidate,
+							new_diff,
+							candidate,
+							region_index,
+							painter_index,
+						));
+					}
+				}
+				ProcessResult::Ignore => {}
+				ProcessResult::Done => num_done += 1,
+				ProcessResult::Error(err) => {
+					panic!("{}", err);
+				}
+			}
+		}
+		best_candidate
+	}
+}
+
+impl Drop for CandidateWorkers {
+	fn drop(&mut self) {
+		// Closing the job channels lets the threads finish
+		self.job_senders.clear();
+		for handle in self.handles.drain(..) {
+			handle.join().expect("joining candidate thread");
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::HashMap;
+
+	use super::*;
+	use crate::generator::painter::Painter;
+	use crate::generator::utils::acceptance::Acceptance;
+	use crate::generator::utils::diff::DiffMetric;
+	use crate::generator::utils::units::WeightedValue;
+
+	struct PanickingPainter;
+
+	impl Painter for PanickingPainter {
+		fn get_shape(
+			&self,
+			_canvas: &RgbImage,
+			_iteration: u32,
+			_seed_map: &RgbImage,
+			_error_map: Option<&ErrorMap>,
+		) -> Result<Shape, &str> {
+			panic!("painter panicked");
+		}
+
+		fn paint_shape(&self, _canvas: &RgbImage, _shape: &Shape) -> Result<Patch, &str> {
+			Err("not painted")
+		}
+
+		fn get_metadata(&self) -> HashMap<String, String> {
+			HashMap::new()
+		}
+	}
+
+	#[test]
+	#[should_panic(expected = "Candidate thread panicked")]
+	fn test_paint_fails_when_painter_panics() {
+		let painter: Box<dyn Painter + Send + Sync> = Box::new(PanickingPainter);
+		let regions = Arc::new(vec![vec![WeightedValue {
+			value: painter,
+			weight: 1.0,
+		}]]);
+		let target = Arc::new(RgbImage::new(4, 4));
+		let workers =
+			CandidateWorkers::new(2, &regions, &target, &Arc::new(None), &Arc::new(None), &Arc::new(None));
+
+		let current = Arc::new(RgbImage::new(4, 4));
+		let diff_map = Arc::new(DiffMap::new(&current, &target, DiffMetric::Luma, None));
+		let acceptor = Acceptor::new(Acceptance::Greedy, 0.0, 0.0);
+		workers.paint(&current, &diff_map, &Arc::new(None), 1.0, &acceptor, vec![(0, 0, 0, 0), (1, 1, 0, 0)]);
+	}
+}
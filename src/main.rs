@@ -1,20 +1,37 @@
 use std::env;
+use std::fs;
 use std::path::PathBuf;
 use std::string::ToString;
+use std::sync::OnceLock;
 
-use image::GenericImageView;
+use ab_glyph::FontArc;
+use image::{DynamicImage, GenericImageView};
 use structopt::StructOpt;
 
-use generator::painter::{circle::CirclePainter, rect::RectPainter, stroke::StrokePainter};
+use generator::genetic::Optimizer;
+use generator::painter::{
+	brush::BrushPainter, circle::CirclePainter, ellipse::EllipsePainter, glyph::GlyphPainter,
+	line::LinePainter, polygon::PolygonPainter, rect::RectPainter, stamp::StampPainter,
+	stroke::StrokePainter, triangle::TrianglePainter, Painter, PainterType,
+};
+use generator::utils::acceptance::Acceptance;
 use generator::utils::color::BlendingMode;
+use generator::utils::coloring::ColorMode;
+use generator::utils::diff::DiffMetric;
 use generator::utils::files;
+use generator::utils::files::checkpoint::Checkpoint;
+use generator::utils::fill::FillType;
+use generator::utils::image::to_alpha_mask;
 use generator::utils::parsing::{
-	parse_color, parse_color_matrix, parse_scale, parse_size_margins, parse_weighted_blending_mode,
-	parse_weighted_float_pair, parse_weighted_size_pair,
+	parse_color, parse_color_matrix, parse_float, parse_region, parse_scale, parse_size_margins,
+	parse_weighted_blending_mode, parse_weighted_fill_type, parse_weighted_float_pair,
+	parse_weighted_integer_pair, parse_weighted_painter_type, parse_weighted_path, parse_weighted_size_pair,
 };
+use generator::utils::placement::Placement;
 use generator::utils::random::get_random_seed;
+use generator::utils::regions::{RegionDefinition, RegionWeight};
 use generator::utils::units::{Margins, SizeUnit, WeightedValue};
-use generator::{Generator, ProcessCallbackResult};
+use generator::{Generator, Painters, ProcessCallbackResult, ProcessState};
 
 mod generator;
 
@@ -51,10 +68,18 @@ struct Opt {
 	///
 	/// In general, the higher the number of candidates, the better the resulting images, at a cost of higher CPU usage.
 	///
-	/// When set to `0`, this uses the number of available cores in the CPU.
+	/// When set to `0`, this uses the number of available cores in the CPU. The number of candidates changes the resulting image, so it's written to the output image metadata; use the same number, along with the same `--rng-seed`, to reproduce an image on any computer.
 	#[structopt(short, long, default_value = "0")]
 	candidates: usize,
 
+	/// Number of threads used to paint candidates in parallel.
+	///
+	/// Unlike `--candidates`, this doesn't change the resulting image, only how fast it's generated.
+	///
+	/// When set to `0`, this uses the number of available cores in the CPU.
+	#[structopt(long, default_value = "0")]
+	threads: usize,
+
 	/// Expected difference score to reach, indicating the desired difference from the new generated image to the target image. New candidates are generated continuously until the resulting difference is below this threshold.
 	///
 	/// On each successfull image generation, the new diff value is generated by calculating the color difference of every pixel. For example, for a completely white target image, a completely black image has 100% difference, while a gray image would have 50% difference.
@@ -63,6 +88,56 @@ struct Opt {
 	#[structopt(short, long, default_value = "0", parse(try_from_str = parse_scale))]
 	diff: f64,
 
+	/// How the difference between the new generated image and the target image is measured.
+	///
+	/// With `luma`, the difference of each color channel is weighted by its perceived brightness. The other metrics convert colors to the CIELAB color space, and measure their difference using the Delta-E formulas from 1976 (`lab-de76`), 1994 (`lab-de94`), or 2000 (`lab-de2000`). These match human perception of color more closely, with `lab-de2000` being the most accurate, but are also slower to calculate. With `ssim`, the structural similarity (SSIM) of the brightness around each pixel is compared instead; rather than favoring blurry averages, this rewards preserving edges and textures, at the cost of ignoring hue. Its difference is `1 - SSIM`.
+	///
+	/// Delta-E differences are divided by 100, so a completely black image still has 100% difference from a completely white one; some color pairs can go above 100% difference. The metric also applies to `--diff`, and is written to the output image metadata.
+	///
+	/// Possible values: `luma`, `lab-de76`, `lab-de94`, `lab-de2000`, `ssim`
+	#[structopt(long, default_value = "luma")]
+	diff_metric: DiffMetric,
+
+	/// A grayscale image with how much each part of the target matters when calculating differences.
+	///
+	/// White areas have full weight, darker areas have less weight, and black areas are ignored entirely. This allows important parts of the image (e.g. faces or logos) to be matched first, while the rest can stay loose. The map is resized to match the target image.
+	///
+	/// With an importance map, the difference is the weighted average of the difference of all pixels, so `--diff` targets still work the same way.
+	#[structopt(long, parse(from_os_str))]
+	importance_map: Option<PathBuf>,
+
+	/// A grayscale image with where new elements can be painted.
+	///
+	/// Elements are only painted over white areas of the mask, while black areas are left untouched; anything in between is painted partially. This allows painting over only part of the image, e.g. repainting the subject of a photo passed via `--input` while keeping its original background. The mask is resized to match the target image.
+	///
+	/// Elements are still placed anywhere inside the `--margins`, so a small mask can make most new candidates fail; in that case, narrowing the margins around the mask makes the generation faster.
+	#[structopt(long, parse(from_os_str))]
+	paint_mask: Option<PathBuf>,
+
+	/// A color-coded image of regions that are painted with their own painter options.
+	///
+	/// Each color used in `--region` marks a region; pixels of any other color are painted with the main painter options. The map is resized to match the target image, without blending colors.
+	///
+	/// Elements of each region are placed inside the bounding box of its pixels, but can still paint outside of it.
+	#[structopt(long, parse(from_os_str))]
+	label_map: Option<PathBuf>,
+
+	/// The painter options for a region of the `--label-map`, as a color and a list of options separated by `=`.
+	///
+	/// For example, `--region "ff0000=--painter circles --painter-radius 1-4"` paints the red pixels of the label map with small circles. Any painter option can be used (e.g. `--painter`, `--painter-alpha`, `--color-seed`), and options not set use their default values rather than the main ones. The `--margins` of a region are always the bounding box of its pixels, and its `--color-mode` is always the main one.
+	///
+	/// Colors can be in any format accepted by `--background-color`, and must be present in the label map. This can be repeated for any number of regions.
+	#[structopt(long, requires = "label-map", parse(try_from_str = parse_region))]
+	region: Vec<RegionDefinition>,
+
+	/// How likely each region of the `--label-map` is to be picked for painting on each try.
+	///
+	/// With `area`, regions are picked in proportion to their number of pixels. With `error`, they're picked in proportion to their remaining difference from the target, so regions that are already close to the target are painted less often.
+	///
+	/// Possible values: `area`, `error`
+	#[structopt(long, default_value = "area")]
+	region_weight: RegionWeight,
+
 	/// Amount of color from the original target image to use as a "seed" when deciding on what color to use when painting a new candidate. With this set to `0`; the algorithm will try painting with a completely random new color; with this set to `1`, the algorithm will use the color already found in the target color; and everything in between is a blend of the two.
 	///
 	/// Using a higher color seed number causes the algorithm to generate valid candidates much faster, and thus create a new image that is closer to the target in shorter time. It does decrease the randomness of the output image, and could in some ways be seen as "cheating" as the algorithm isn't painting blindly anymore.
@@ -71,6 +146,76 @@ struct Opt {
 	#[structopt(long, default_value = "0", parse(try_from_str = parse_scale))]
 	color_seed: f64,
 
+	/// How the colors of new elements are decided.
+	///
+	/// With `random`, colors are random, blended with the target color as set by `--color-seed`. With `seed`, colors are always taken from the target, the same as `--color-seed 1`. With `optimal`, each element is given the flat color that brings the pixels it covers closest to the target, taking its alpha into account; this removes color as a random variable and greatly increases the success rate, at the cost of painting each candidate twice. The color mode applies to every region. Optimal colors replace any `--painter-fill` gradients, and are only used for elements with the `normal` blending mode.
+	///
+	/// Possible values: `random`, `seed`, `optimal`
+	#[structopt(long, default_value = "random")]
+	color_mode: ColorMode,
+
+	/// Where new elements are placed.
+	///
+	/// With `uniform`, elements are placed anywhere inside the painting area (see `--margins`) with the same probability. With `error`, they're placed in proportion to how different each part of the current image is from the target, so new candidates are more likely to land where they can improve the result. This increases the success rate, especially once most of the image is already close to the target, and is also guided by `--importance-map`.
+	///
+	/// Possible values: `uniform`, `error`
+	#[structopt(long, default_value = "uniform")]
+	placement: Placement,
+
+	/// Number of times each new accepted element is refined.
+	///
+	/// When a candidate is accepted, its element is randomly changed this many times (moved, resized, recolored, or made more or less opaque), keeping any changes that bring it closer to the target. This results in fewer, better fitting elements, at the cost of more painting per generation.
+	///
+	/// When set to `0`, elements are used as originally painted.
+	#[structopt(long, default_value = "0")]
+	refine_steps: u32,
+
+	/// How the image is searched for.
+	///
+	/// With `incremental`, new elements are painted over the current image one at a time, and kept when they're accepted (see `--acceptance`). With `genetic`, a genetic algorithm is used instead: a population of `--population` individuals, each a list of `--genetic-shapes` elements, is evolved by crossing over the lists of the best individuals and mutating some of their elements (see `--mutation-rate`). The result is the best individual painted over the background; on each try, a new population is created, and tries that improve the best individual count as generations.
+	///
+	/// The genetic optimizer only uses the main painter options, and ignores `--acceptance`, `--placement`, `--refine-steps`, `--color-mode optimal`, and regions. With more than one painter, the number of elements of the result painted by each painter is added to the metadata, instead of their number of generations.
+	///
+	/// Possible values: `incremental`, `genetic`
+	#[structopt(long, default_value = "incremental")]
+	optimizer: Optimizer,
+
+	/// Number of individuals in the population of the `genetic` optimizer.
+	#[structopt(long, default_value = "32")]
+	population: usize,
+
+	/// Number of elements painted by each individual of the `genetic` optimizer.
+	#[structopt(long, default_value = "100")]
+	genetic_shapes: usize,
+
+	/// Chance of each element of a new individual of the `genetic` optimizer being mutated: either changed (moved, resized, recolored, or made more or less opaque), or replaced with a new random element.
+	///
+	/// Possible values: `0`..`1`
+	#[structopt(long, default_value = "0.02", parse(try_from_str = parse_scale))]
+	mutation_rate: f64,
+
+	/// How candidates are accepted.
+	///
+	/// With `greedy`, a candidate is only accepted when it's closer to the target than the current image. This can get stuck, as any element that would lead to a better image later but makes it slightly worse now is never used. With `annealing` (simulated annealing), worse candidates can also be accepted, with a chance that gets lower the worse they are, and the lower the temperature is (see `--annealing-temperature` and `--annealing-cooling-rate`). The temperature is shown in the progress output and written to the output image metadata.
+	///
+	/// Possible values: `greedy`, `annealing`
+	#[structopt(long, default_value = "greedy")]
+	acceptance: Acceptance,
+
+	/// Starting temperature of the `annealing` acceptance.
+	///
+	/// This is in the same unit as differences, so a temperature of `0.0001` accepts a candidate that is 0.01% worse than the current image around 37% of the time, and one that is 0.05% worse less than 1% of the time.
+	#[structopt(long, default_value = "0.0001", parse(try_from_str = parse_float))]
+	annealing_temperature: f64,
+
+	/// How much the temperature of the `annealing` acceptance is multiplied by after each try.
+	///
+	/// Lower values cool down faster; with the default of `0.999`, the temperature is divided by around 20 every 3000 tries. With `1`, the temperature never changes.
+	///
+	/// Possible values: `0`..`1`
+	#[structopt(long, default_value = "0.999", parse(try_from_str = parse_scale))]
+	annealing_cooling_rate: f64,
+
 	/// Outputs benchmark results.
 	///
 	/// With this flag, the application will gather some benchmark metrics and output them after it runs. This is useful to measure efficiency of the algorithm as it evolves.
@@ -166,6 +311,18 @@ struct Opt {
 	#[structopt(long)]
 	save_often: bool,
 
+	/// The filename for a checkpoint to be saved to, if any.
+	///
+	/// When present, the state of the generation process is saved to this file periodically: the current image, the try and generation counters, the position of the random number generator, the time elapsed, and all arguments used. A stopped process can then continue exactly where the checkpoint was saved, with `resume <filename>` as the only argument.
+	///
+	/// The checkpoint is saved as a PNG image. If the file already exists, it is overwritten without warning. Checkpoints can't be used with the `genetic` optimizer.
+	#[structopt(long, parse(from_os_str))]
+	checkpoint: Option<PathBuf>,
+
+	/// Number of tries between checkpoints, when `--checkpoint` is used.
+	#[structopt(long, default_value = "1000")]
+	checkpoint_interval: u32,
+
 	/// The new size of the output image, as a scale of the target image.
 	///
 	/// This is useful if one wants the result image to be either smaller or larger than the target image.
@@ -182,15 +339,17 @@ struct Opt {
 	#[structopt(long, default_value = "normal", default_value = "normal", parse(try_from_str = parse_weighted_blending_mode))]
 	blending_mode: Vec<WeightedValue<BlendingMode>>,
 
-	/// Painter to be used.
+	/// Painter(s) to be used, either as a single entry, or as a list.
 	///
 	/// This determines how new candidates will be painted when trying to approximate the target image. A selection of basic painters currently exist.
 	///
 	/// Painters can be further configured with other `--painter-*` arguments.
 	///
-	/// Possible values: `rects`, `circles`, `strokes`
-	#[structopt(short, long, possible_values = &["circles", "strokes", "rects"], default_value = "rects")]
-	painter: String,
+	/// When more than one painter is passed, one painter is randomly picked for each new candidate, based on their weights (e.g. `circles@2 rects`). The number of generations produced by each painter, the painter that produced each generation, and the metadata of every painter are added to the metadata of the result image.
+	///
+	/// Possible values: `rects`, `circles`, `strokes`, `triangles`, `polygons`, `ellipses`, `brushes`, `lines`, `stamps`, `glyphs`
+	#[structopt(short, long, default_value = "rects", parse(try_from_str = parse_weighted_painter_type))]
+	painter: Vec<WeightedValue<PainterType>>,
 
 	/// Opacity to use when painting new images.
 	///
@@ -208,7 +367,7 @@ struct Opt {
 
 	/// Radius to use when painting elements, when applicable.
 	///
-	/// This applies when `--painter` is set to `circles`. In case a percentage value is passed, it is relative to either the width or height of the result image (whichever is smaller).
+	/// This applies when `--painter` is set to `circles` or `polygons`. In case a percentage value is passed, it is relative to either the width or height of the result image (whichever is smaller). For `polygons`, this is the maximum distance from the center of the shape to any of its vertices.
 	///
 	/// The argument is a list, so it can also feature more than one value (or ranges, or a mix of values or ranges), in which case one new entry is randomly picked for each new paint.
 	#[structopt(long, default_value = "0%-50%", parse(try_from_str = parse_weighted_size_pair))]
@@ -220,9 +379,203 @@ struct Opt {
 	#[structopt(long, default_value = "0.0", allow_hyphen_values = true)]
 	painter_radius_bias: f64,
 
+	/// Horizontal radius to use when painting elements, when applicable.
+	///
+	/// This applies when `--painter` is set to `ellipses`. In case a percentage value is passed, it is relative to the width of the result image. The radius is measured before the ellipse is rotated by `--painter-rotation`.
+	///
+	/// The argument is a list, so it can also feature more than one value (or ranges, or a mix of values or ranges), in which case one new entry is randomly picked for each new paint.
+	#[structopt(long, default_value = "0%-50%", parse(try_from_str = parse_weighted_size_pair))]
+	painter_radius_x: Vec<WeightedValue<(SizeUnit, SizeUnit)>>,
+
+	/// Bias for distribution in `--painter-radius-x` ranges.
+	///
+	/// A bias of 0.0 means a normal, linear distribution; -1.0 = quad bias towards range start; 1.0 = quad bias towards range end.
+	#[structopt(long, default_value = "0.0", allow_hyphen_values = true)]
+	painter_radius_x_bias: f64,
+
+	/// Vertical radius to use when painting elements, when applicable.
+	///
+	/// This applies when `--painter` is set to `ellipses`. In case a percentage value is passed, it is relative to the height of the result image. The radius is measured before the ellipse is rotated by `--painter-rotation`.
+	///
+	/// The argument is a list, so it can also feature more than one value (or ranges, or a mix of values or ranges), in which case one new entry is randomly picked for each new paint.
+	#[structopt(long, default_value = "0%-50%", parse(try_from_str = parse_weighted_size_pair))]
+	painter_radius_y: Vec<WeightedValue<(SizeUnit, SizeUnit)>>,
+
+	/// Bias for distribution in `--painter-radius-y` ranges.
+	///
+	/// A bias of 0.0 means a normal, linear distribution; -1.0 = quad bias towards range start; 1.0 = quad bias towards range end.
+	#[structopt(long, default_value = "0.0", allow_hyphen_values = true)]
+	painter_radius_y_bias: f64,
+
+	/// Rotation to use when painting elements, in degrees, when applicable.
+	///
+	/// This applies when `--painter` is set to `ellipses`, `glyphs`, `rects`, `stamps`, or `strokes`. Elements are rotated clockwise around their center, and are kept inside the painting area (see `--margins`) after rotation.
+	///
+	/// The argument is a list, so it can also feature more than one value (or ranges, or a mix of values or ranges), in which case one new entry is randomly picked for each new paint.
+	#[structopt(long, default_value = "0", parse(try_from_str = parse_weighted_float_pair))]
+	painter_rotation: Vec<WeightedValue<(f64, f64)>>,
+
+	/// Bias for distribution in `--painter-rotation` ranges.
+	///
+	/// A bias of 0.0 means a normal, linear distribution; -1.0 = quad bias towards range start; 1.0 = quad bias towards range end.
+	#[structopt(long, default_value = "0.0", allow_hyphen_values = true)]
+	painter_rotation_bias: f64,
+
+	/// Number of vertices to use when painting polygons.
+	///
+	/// This applies when `--painter` is set to `polygons`. Values lower than `3` are treated as `3`.
+	///
+	/// The argument is a list, so it can also feature more than one value (or ranges, or a mix of values or ranges), in which case one new entry is randomly picked for each new paint.
+	#[structopt(long, default_value = "3-8", parse(try_from_str = parse_weighted_integer_pair))]
+	painter_vertices: Vec<WeightedValue<(u32, u32)>>,
+
+	/// Irregularity of painted polygons.
+	///
+	/// This applies when `--painter` is set to `polygons`. With `0`, polygons are regular, with all vertices evenly spaced around the center. Higher values shift the angle of each vertex, and pull them towards the center, creating star-like or blob-like shapes (some of them concave). A value of `1` means fully irregular shapes.
+	///
+	/// Possible values: `0`..`1`
+	///
+	/// The argument is a list, so it can also feature more than one value (or ranges, or a mix of values or ranges), in which case one new entry is randomly picked for each new paint.
+	#[structopt(long, default_value = "0.5", parse(try_from_str = parse_weighted_float_pair))]
+	painter_irregularity: Vec<WeightedValue<(f64, f64)>>,
+
+	/// Bias for distribution in `--painter-irregularity` ranges.
+	///
+	/// A bias of 0.0 means a normal, linear distribution; -1.0 = quad bias towards range start; 1.0 = quad bias towards range end.
+	#[structopt(long, default_value = "0.0", allow_hyphen_values = true)]
+	painter_irregularity_bias: f64,
+
+	/// Thickness of painted brush strokes.
+	///
+	/// This applies when `--painter` is set to `brushes` or `lines`. In case a percentage value is passed, it is relative to either the width or height of the result image (whichever is smaller). For `brushes`, this is the thickness at the thickest point of the stroke; see `--painter-taper`. For `lines`, percentages that result in values below one pixel (e.g. `0.1%`) can be used for hairlines.
+	///
+	/// The argument is a list, so it can also feature more than one value (or ranges, or a mix of values or ranges), in which case one new entry is randomly picked for each new paint.
+	#[structopt(long, default_value = "1%-5%", parse(try_from_str = parse_weighted_size_pair))]
+	painter_thickness: Vec<WeightedValue<(SizeUnit, SizeUnit)>>,
+
+	/// Bias for distribution in `--painter-thickness` ranges.
+	///
+	/// A bias of 0.0 means a normal, linear distribution; -1.0 = quad bias towards range start; 1.0 = quad bias towards range end.
+	#[structopt(long, default_value = "0.0", allow_hyphen_values = true)]
+	painter_thickness_bias: f64,
+
+	/// Length of painted brush strokes or lines.
+	///
+	/// This applies when `--painter` is set to `brushes` or `lines`. In case a percentage value is passed, it is relative to either the width or height of the result image (whichever is smaller). The length is measured as the distance between the start and the end of the element, so curved brush strokes are a bit longer.
+	///
+	/// The argument is a list, so it can also feature more than one value (or ranges, or a mix of values or ranges), in which case one new entry is randomly picked for each new paint.
+	#[structopt(long, default_value = "5%-30%", parse(try_from_str = parse_weighted_size_pair))]
+	painter_length: Vec<WeightedValue<(SizeUnit, SizeUnit)>>,
+
+	/// Bias for distribution in `--painter-length` ranges.
+	///
+	/// A bias of 0.0 means a normal, linear distribution; -1.0 = quad bias towards range start; 1.0 = quad bias towards range end.
+	#[structopt(long, default_value = "0.0", allow_hyphen_values = true)]
+	painter_length_bias: f64,
+
+	/// Taper of painted brush strokes.
+	///
+	/// This applies when `--painter` is set to `brushes`. With `0`, strokes have the same thickness from start to end. Higher values make the ends of the stroke thinner than its middle; a value of `1` means strokes end in a point.
+	///
+	/// Possible values: `0`..`1`
+	///
+	/// The argument is a list, so it can also feature more than one value (or ranges, or a mix of values or ranges), in which case one new entry is randomly picked for each new paint.
+	#[structopt(long, default_value = "0.5", parse(try_from_str = parse_weighted_float_pair))]
+	painter_taper: Vec<WeightedValue<(f64, f64)>>,
+
+	/// Bias for distribution in `--painter-taper` ranges.
+	///
+	/// A bias of 0.0 means a normal, linear distribution; -1.0 = quad bias towards range start; 1.0 = quad bias towards range end.
+	#[structopt(long, default_value = "0.0", allow_hyphen_values = true)]
+	painter_taper_bias: f64,
+
+	/// Curvature of painted brush strokes.
+	///
+	/// This applies when `--painter` is set to `brushes`. Strokes are painted along a cubic Bezier curve; with `0`, strokes are straight lines. Higher values bend the curve more, with a value of `1` allowing its control points to be as far away from the line as the length of the stroke.
+	///
+	/// The argument is a list, so it can also feature more than one value (or ranges, or a mix of values or ranges), in which case one new entry is randomly picked for each new paint.
+	#[structopt(long, default_value = "0-0.5", parse(try_from_str = parse_weighted_float_pair))]
+	painter_curvature: Vec<WeightedValue<(f64, f64)>>,
+
+	/// Bias for distribution in `--painter-curvature` ranges.
+	///
+	/// A bias of 0.0 means a normal, linear distribution; -1.0 = quad bias towards range start; 1.0 = quad bias towards range end.
+	#[structopt(long, default_value = "0.0", allow_hyphen_values = true)]
+	painter_curvature_bias: f64,
+
+	/// Orients brush strokes along the edges of the target image.
+	///
+	/// This applies when `--painter` is set to `brushes`. Instead of a random direction, each stroke follows the direction of the edges found in the target image at the center of the stroke. Strokes painted in flat areas (without any edges) still use a random direction.
+	#[structopt(long)]
+	painter_follow_edges: bool,
+
+	/// Angle of painted lines, in degrees.
+	///
+	/// This applies when `--painter` is set to `lines`. An angle of `0` means horizontal lines, with higher values rotating them clockwise. Since lines look the same in both directions, `0-180` covers all possible angles.
+	///
+	/// The argument is a list, so it can also feature more than one value (or ranges, or a mix of values or ranges), in which case one new entry is randomly picked for each new paint. Entries can be separated by spaces or commas; for example, `0,90` paints cross-hatching, and `0@1,45@1,90@1` uses three fixed angles with the same weight.
+	#[structopt(long, default_value = "0-180", use_delimiter = true, parse(try_from_str = parse_weighted_float_pair))]
+	painter_angle: Vec<WeightedValue<(f64, f64)>>,
+
+	/// Bias for distribution in `--painter-angle` ranges.
+	///
+	/// A bias of 0.0 means a normal, linear distribution; -1.0 = quad bias towards range start; 1.0 = quad bias towards range end.
+	#[structopt(long, default_value = "0.0", allow_hyphen_values = true)]
+	painter_angle_bias: f64,
+
+	/// Image files to use as stamps.
+	///
+	/// This applies when `--painter` is set to `stamps`, in which case it is required. Each image is used as an alpha mask for the paint: white areas are painted, while black or transparent areas are left untouched. Images are tinted with the paint color, so they are best created in grayscale.
+	///
+	/// The argument is a list, so it can also feature more than one file, in which case one new entry is randomly picked for each new paint. Files can also have weights (e.g. `charcoal.png@2`).
+	#[structopt(long, parse(try_from_str = parse_weighted_path))]
+	painter_stamp: Vec<WeightedValue<PathBuf>>,
+
+	/// Scale of painted stamps.
+	///
+	/// This applies when `--painter` is set to `stamps`. A scale of `1` paints stamps at the original size of their image files, in pixels. Since the size of the result image is not taken into account, this usually needs to be adjusted when using `--scale`.
+	///
+	/// The argument is a list, so it can also feature more than one value (or ranges, or a mix of values or ranges), in which case one new entry is randomly picked for each new paint.
+	#[structopt(long, default_value = "1", parse(try_from_str = parse_weighted_float_pair))]
+	painter_scale: Vec<WeightedValue<(f64, f64)>>,
+
+	/// Bias for distribution in `--painter-scale` ranges.
+	///
+	/// A bias of 0.0 means a normal, linear distribution; -1.0 = quad bias towards range start; 1.0 = quad bias towards range end.
+	#[structopt(long, default_value = "0.0", allow_hyphen_values = true)]
+	painter_scale_bias: f64,
+
+	/// Font file to use when painting glyphs.
+	///
+	/// This applies when `--painter` is set to `glyphs`, in which case it is required. Both TrueType (`.ttf`) and OpenType (`.otf`) files are supported.
+	#[structopt(long, parse(from_os_str))]
+	painter_font: Option<PathBuf>,
+
+	/// Characters to use when painting glyphs.
+	///
+	/// This applies when `--painter` is set to `glyphs`. Each new paint picks one random character from this text; repeating a character makes it more likely to be picked. Whitespace, and characters not supported by the font, are ignored.
+	///
+	/// If not set, all characters supported by the font are used.
+	#[structopt(long, default_value = "")]
+	painter_characters: String,
+
+	/// Font size to use when painting glyphs.
+	///
+	/// This applies when `--painter` is set to `glyphs`. In case a percentage value is passed, it is relative to either the width or height of the result image (whichever is smaller). Like in other software, the font size is the height of a line of text, so most glyphs are smaller than that.
+	///
+	/// The argument is a list, so it can also feature more than one value (or ranges, or a mix of values or ranges), in which case one new entry is randomly picked for each new paint.
+	#[structopt(long, default_value = "5%-20%", parse(try_from_str = parse_weighted_size_pair))]
+	painter_font_size: Vec<WeightedValue<(SizeUnit, SizeUnit)>>,
+
+	/// Bias for distribution in `--painter-font-size` ranges.
+	///
+	/// A bias of 0.0 means a normal, linear distribution; -1.0 = quad bias towards range start; 1.0 = quad bias towards range end.
+	#[structopt(long, default_value = "0.0", allow_hyphen_values = true)]
+	painter_font_size_bias: f64,
+
 	/// Width to use when painting elements.
 	///
-	/// This applies when `--painter` is set to `rects` or `strokes`.
+	/// This applies when `--painter` is set to `rects`, `strokes`, or `triangles`. For `triangles`, this is the width of the box where the triangle vertices are randomly placed.
 	///
 	/// The argument is a list, so it can also feature more than one value (or ranges, or a mix of values or ranges), in which case one new entry is randomly picked for each new paint.
 	#[structopt(long, default_value = "0%-100%", parse(try_from_str = parse_weighted_size_pair))]
@@ -236,7 +589,7 @@ struct Opt {
 
 	/// Height to use when painting elements.
 	///
-	/// This applies when `--painter` is set to `rects` or `strokes`.
+	/// This applies when `--painter` is set to `rects`, `strokes`, or `triangles`. For `triangles`, this is the height of the box where the triangle vertices are randomly placed.
 	///
 	/// The argument is a list, so it can also feature more than one value (or ranges, or a mix of values or ranges), in which case one new entry is randomly picked for each new paint.
 	#[structopt(long, default_value = "0%-100%", parse(try_from_str = parse_weighted_size_pair))]
@@ -256,6 +609,16 @@ struct Opt {
 	#[structopt(long)]
 	painter_disable_anti_alias: bool,
 
+	/// Fill type(s) to use when painting elements, either as a single entry, or as a list.
+	///
+	/// This applies to all painters. With `flat`, elements are painted with a single color. With `linear`, they are painted with a gradient between two colors going across the element in a random direction; with `radial`, the gradient goes from the center of the element to its edges.
+	///
+	/// The second color of a gradient is picked the same way as the first: a random color, mixed with the color of the target image at the opposite end of the gradient according to `--color-seed`.
+	///
+	/// Possible values: `flat`, `linear`, `radial`
+	#[structopt(long, default_value = "flat", parse(try_from_str = parse_weighted_fill_type))]
+	painter_fill: Vec<WeightedValue<FillType>>,
+
 	/// Height of paint waves, when applicable.
 	///
 	/// This applies when `--painter` is set to `strokes`. In case a percentage value is passed, it is always relative to the width of the result image.
@@ -297,18 +660,96 @@ struct Opt {
 	margins: Margins<SizeUnit>,
 }
 
-fn get_options() -> Opt {
-	return Opt::from_args();
+fn get_options() -> &'static Opt {
+	static OPTIONS: OnceLock<Opt> = OnceLock::new();
+	OPTIONS.get_or_init(|| Opt::from_iter(get_args()))
+}
+
+/// The command line arguments; when resuming, the ones the checkpoint was started with.
+fn get_args() -> Vec<String> {
+	match get_resume_checkpoint() {
+		Some(checkpoint) => env::args().take(1).chain(checkpoint.args.iter().cloned()).collect(),
+		None => env::args().collect(),
+	}
+}
+
+/// The checkpoint to resume from, when running with `resume <filename>` as the only argument.
+fn get_resume_checkpoint() -> Option<&'static Checkpoint> {
+	static CHECKPOINT: OnceLock<Option<Checkpoint>> = OnceLock::new();
+	CHECKPOINT
+		.get_or_init(|| {
+			let args: Vec<String> = env::args().collect();
+			if args.len() == 3 && args[1] == "resume" {
+				let checkpoint_file = PathBuf::from(&args[2]);
+				Some(Checkpoint::read(&checkpoint_file).unwrap_or_else(|err| {
+					panic!("Cannot resume from checkpoint file {:?}: {}, exiting", checkpoint_file, err)
+				}))
+			} else {
+				None
+			}
+		})
+		.as_ref()
+}
+
+/// Names each painter of each region for the metadata, by its type; the painters of the regions of the
+/// label map also have the color of their region, and painters of the same type in the same region are
+/// numbered.
+fn get_painter_labels(options: &Opt) -> Vec<Vec<String>> {
+	let get_labels = |painters: &[WeightedValue<PainterType>], suffix: String| {
+		painters
+			.iter()
+			.enumerate()
+			.map(|(index, painter)| {
+				if painters.iter().filter(|other| other.value == painter.value).count() > 1 {
+					format!("{} {}{}", painter.value, index + 1, suffix)
+				} else {
+					format!("{}{}", painter.value, suffix)
+				}
+			})
+			.collect::<Vec<String>>()
+	};
+	let mut labels = vec![get_labels(&options.painter, String::new())];
+	for region in &options.region {
+		let color = region.0;
+		let suffix = format!(" (#{:02x}{:02x}{:02x})", color.0, color.1, color.2);
+		labels.push(get_labels(&get_region_options(options, &region.1).painter, suffix));
+	}
+	labels
+}
+
+/// Parses the painter options of a region. Options not set use their default values.
+fn get_region_options(options: &Opt, region_args: &[String]) -> Opt {
+	// Generation limits don't apply to regions, but are needed to parse the options
+	let args = [env::args().next().unwrap_or_default(), options.target.to_string_lossy().to_string()]
+		.into_iter()
+		.chain(["--generations".to_string(), "1".to_string()])
+		.chain(region_args.iter().cloned());
+	Opt::from_iter_safe(args).unwrap_or_else(|err| err.exit())
 }
 
 fn on_processed(generator: &Generator, result: ProcessCallbackResult) {
+	let options = get_options();
+
+	// Checkpoints are saved every few tries, successful or not
+	if let (Some(checkpoint_path), Some(state)) = (&options.checkpoint, &result.state) {
+		if !result.is_final && result.num_tries.is_multiple_of(options.checkpoint_interval.max(1)) {
+			let checkpoint = Checkpoint {
+				args: get_args().into_iter().skip(1).collect(),
+				state: ProcessState {
+					painter_runs: generator.get_painter_runs().to_vec(),
+					..state.clone()
+				},
+				image: generator.get_current(),
+			};
+			checkpoint.write(checkpoint_path).expect("writing checkpoint file");
+		}
+	}
+
 	// Ignore unsuccessful generations
 	if !result.is_success {
 		return;
 	}
 
-	let options = get_options();
-
 	// Only write the file if it's the final generation, or it's meant to save often
 	if !result.is_final && !options.save_often {
 		return;
@@ -332,12 +773,82 @@ fn on_processed(generator: &Generator, result: ProcessCallbackResult) {
 				result.time_elapsed / (result.num_tries as f32) * 1000.0,
 				result.diff * 100.0
 			),
-			format!("Command line: {}", env::args().collect::<Vec<String>>().join(" ")),
+			format!("Command line: {}", get_args().join(" ")),
+			format!("Difference metric: {}", options.diff_metric),
 		];
+		if options.optimizer == Optimizer::Incremental {
+			comments.push(format!("Candidates: {}", get_candidates(options)));
+		}
+		if options.optimizer == Optimizer::Genetic {
+			comments.push(format!(
+				"Genetic optimizer: population of {} individuals of {} elements, {} mutation rate",
+				options.population, options.genetic_shapes, options.mutation_rate
+			));
+		}
+		if options.acceptance == Acceptance::Annealing {
+			comments.push(format!("Annealing temperature: {:.3e}", result.temperature));
+		}
 
 		// Add painter-specific metadata
-		for (key, value) in result.metadata {
-			comments.push(format!("{}: {}", key, value));
+		let painter_labels = get_painter_labels(options);
+		let has_many_painters = painter_labels.iter().map(|labels| labels.len()).sum::<usize>() > 1;
+		if has_many_painters && !result.painter_generations.is_empty() {
+			let painter_generations = painter_labels[0]
+				.iter()
+				.zip(&result.painter_generations[0])
+				.map(|(label, generations)| format!("{} ({} generations)", label, generations))
+				.collect::<Vec<String>>();
+			comments.push(format!("Painters: {}", painter_generations.join(", ")));
+			for (index, region) in options.region.iter().enumerate() {
+				let color = region.0;
+				let painter_generations = get_region_options(options, &region.1)
+					.painter
+					.iter()
+					.zip(&result.painter_generations[index + 1])
+					.map(|(painter, generations)| format!("{} ({} generations)", painter.value, generations))
+					.collect::<Vec<String>>();
+				comments.push(format!(
+					"Region #{:02x}{:02x}{:02x}: {}",
+					color.0,
+					color.1,
+					color.2,
+					painter_generations.join(", ")
+				));
+			}
+
+			// Winners are written as runs of consecutive generations, e.g. `circles x3, rects, circles x2`
+			let painter_runs = generator
+				.get_painter_runs()
+				.iter()
+				.map(|run| match run.generations {
+					1 => painter_labels[run.region][run.painter].clone(),
+					generations => format!("{} x{}", painter_labels[run.region][run.painter], generations),
+				})
+				.collect::<Vec<String>>();
+			if !painter_runs.is_empty() {
+				comments.push(format!("Painter of each generation: {}", painter_runs.join(", ")));
+			}
+		}
+		if has_many_painters && !result.painter_elements.is_empty() {
+			let painter_elements = painter_labels[0]
+				.iter()
+				.zip(&result.painter_elements)
+				.map(|(label, elements)| format!("{} ({} elements)", label, elements))
+				.collect::<Vec<String>>();
+			comments.push(format!("Painter elements: {}", painter_elements.join(", ")));
+		}
+		for (region_labels, region_metadata) in painter_labels.iter().zip(&result.metadata) {
+			for (label, metadata) in region_labels.iter().zip(region_metadata) {
+				let mut keys = metadata.keys().collect::<Vec<&String>>();
+				keys.sort();
+				for key in keys {
+					if has_many_painters {
+						comments.push(format!("{} {}: {}", label, key, metadata[key]));
+					} else {
+						comments.push(format!("{}: {}", key, metadata[key]));
+					}
+				}
+			}
 		}
 
 		files::write_image_with_metadata(generator.get_current(), output_path, comments);
@@ -347,6 +858,10 @@ fn on_processed(generator: &Generator, result: ProcessCallbackResult) {
 fn main() {
 	let options = get_options();
 
+	if options.checkpoint.is_some() && options.optimizer == Optimizer::Genetic {
+		panic!("Checkpoints can't be used with the genetic optimizer, exiting");
+	}
+
 	// Target
 	let target_file = options.target.as_path();
 	let target_image = image::open(target_file).expect("Cannot open target file {:?}, exiting");
@@ -365,9 +880,106 @@ fn main() {
 		}
 	};
 
+	gen.set_diff_metric(options.diff_metric);
+	println!("Using the {} difference metric.", options.diff_metric);
+
+	gen.set_color_mode(options.color_mode);
+	println!("Using {} colors.", options.color_mode);
+
+	gen.set_placement(options.placement);
+	println!("Using {} placement.", options.placement);
+
+	gen.set_acceptance(options.acceptance, options.annealing_temperature, options.annealing_cooling_rate);
+	match options.acceptance {
+		Acceptance::Greedy => println!("Using greedy acceptance."),
+		Acceptance::Annealing => println!(
+			"Using annealing acceptance, starting at temperature {} with a cooling rate of {}.",
+			options.annealing_temperature, options.annealing_cooling_rate
+		),
+	}
+
+	if options.refine_steps > 0 {
+		gen.set_refine_steps(options.refine_steps);
+		println!("Refining accepted elements with {} steps.", options.refine_steps);
+	}
+
+	// Set importance map
+	if let Some(importance_map) = &options.importance_map {
+		let importance_map_file = importance_map.as_path();
+		let importance_map_image = image::open(importance_map_file).unwrap_or_else(|err| {
+			panic!("Cannot open importance map file {:?}: {}, exiting", importance_map_file, err)
+		});
+
+		println!(
+			"Using importance map image of {:?} with dimensions of {:?}.",
+			importance_map_file,
+			importance_map_image.dimensions()
+		);
+
+		gen.set_importance_map(importance_map_image);
+	}
+
+	// Set paint mask
+	if let Some(paint_mask) = &options.paint_mask {
+		let paint_mask_file = paint_mask.as_path();
+		let paint_mask_image = image::open(paint_mask_file).unwrap_or_else(|err| {
+			panic!("Cannot open paint mask file {:?}: {}, exiting", paint_mask_file, err)
+		});
+
+		println!(
+			"Using paint mask image of {:?} with dimensions of {:?}.",
+			paint_mask_file,
+			paint_mask_image.dimensions()
+		);
+
+		gen.set_paint_mask(paint_mask_image);
+	}
+
+	// Set label map
+	let mut region_bounds = vec![];
+	if let Some(label_map) = &options.label_map {
+		let label_map_file = label_map.as_path();
+		let label_map_image = image::open(label_map_file).unwrap_or_else(|err| {
+			panic!("Cannot open label map file {:?}: {}, exiting", label_map_file, err)
+		});
+
+		println!(
+			"Using label map image of {:?} with dimensions of {:?}.",
+			label_map_file,
+			label_map_image.dimensions()
+		);
+
+		let colors = options.region.iter().map(|region| region.0).collect::<Vec<(u8, u8, u8)>>();
+		region_bounds = gen.set_label_map(label_map_image, &colors);
+		for (color, bounds) in colors.iter().zip(&region_bounds) {
+			if bounds.width == 0 {
+				panic!(
+					"Region color #{:02x}{:02x}{:02x} not found in the label map, exiting",
+					color.0, color.1, color.2
+				);
+			}
+		}
+
+		gen.set_region_weight(options.region_weight);
+		println!("Picking regions by {}.", options.region_weight);
+	}
+
 	// Set input
-	match options.input {
-		Some(input) => {
+	match (get_resume_checkpoint(), &options.input) {
+		(Some(checkpoint), _) => {
+			if checkpoint.image.dimensions() != gen.get_current().dimensions() {
+				panic!("Checkpoint image dimensions don't match the target, exiting");
+			}
+
+			println!(
+				"Resuming from checkpoint after {} tries and {} generations.",
+				checkpoint.state.num_tries, checkpoint.state.num_generations
+			);
+
+			gen.prepopulate_with_image(DynamicImage::ImageRgb8(checkpoint.image.clone()));
+			gen.resume(checkpoint.state.clone());
+		}
+		(None, Some(input)) => {
 			let input_file = input.as_path();
 			let input_image = image::open(input_file).expect("Cannot open input file {:?}, exiting");
 
@@ -379,7 +991,7 @@ fn main() {
 
 			gen.prepopulate_with_image(input_image);
 		}
-		None => {
+		(None, None) => {
 			let color = options.background_color;
 			gen.prepopulate_with_color(color.0, color.1, color.2);
 		}
@@ -390,16 +1002,19 @@ fn main() {
 	println!("Using output image of {:?}.", output_file);
 
 	// Other options
-	let candidates = if options.benchmark {
-		1
-	} else if options.candidates > 0 {
-		options.candidates
+	let candidates = get_candidates(options);
+	if !options.benchmark && options.candidates == 0 {
+		println!("Using auto {} candidates.", candidates);
+	}
+	let threads = if options.threads > 0 {
+		options.threads
 	} else {
-		println!("Using auto {} candidates.", num_cpus::get());
 		num_cpus::get()
 	};
 
-	let rng_seed = if options.rng_seed == 0 {
+	let rng_seed = if let Some(checkpoint) = get_resume_checkpoint() {
+		checkpoint.state.rng_seed
+	} else if options.rng_seed == 0 {
 		get_random_seed()
 	} else {
 		options.rng_seed
@@ -407,79 +1022,294 @@ fn main() {
 	println!("RNG seed is {}.", rng_seed);
 
 	// Process everything
-	// TODO: use actual enums here and use a single object from trait (can't seen to make it work)
+	let mut regions = vec![create_painters(options, rng_seed)];
+	let (width, height) = gen.get_current().dimensions();
+	for (region, bounds) in options.region.iter().zip(region_bounds) {
+		// Regions are painted inside their bounding box
+		let mut region_options = get_region_options(options, &region.1);
+		region_options.margins = Margins::<SizeUnit> {
+			top: SizeUnit::Pixels(bounds.y as i64),
+			right: SizeUnit::Pixels(width as i64 - (bounds.x + bounds.width) as i64),
+			bottom: SizeUnit::Pixels(height as i64 - (bounds.y + bounds.height) as i64),
+			left: SizeUnit::Pixels(bounds.x as i64),
+		};
+		// Color modes apply to the whole image
+		region_options.color_mode = options.color_mode;
+		regions.push(create_painters(&region_options, rng_seed));
+	}
+	match options.optimizer {
+		Optimizer::Incremental => gen.process(
+			options.max_tries,
+			options.generations,
+			options.diff,
+			options.benchmark,
+			candidates,
+			threads,
+			regions,
+			rng_seed,
+			Some(on_processed),
+		),
+		Optimizer::Genetic => gen.evolve(
+			options.max_tries,
+			options.generations,
+			options.diff,
+			threads,
+			options.population,
+			options.genetic_shapes,
+			options.mutation_rate,
+			regions.swap_remove(0),
+			rng_seed,
+			Some(on_processed),
+		),
+	}
+}
+
+/// Finds the number of candidates per try; with benchmarks, a single candidate is always used
+fn get_candidates(options: &Opt) -> usize {
+	if options.benchmark {
+		1
+	} else if options.candidates > 0 {
+		options.candidates
+	} else {
+		num_cpus::get()
+	}
+}
+
+/// Creates all painters set in the painter options, with their weights
+fn create_painters(options: &Opt, rng_seed: u32) -> Painters {
+	options
+		.painter
+		.iter()
+		.map(|painter| WeightedValue {
+			value: create_painter(&painter.value, options, rng_seed),
+			weight: painter.weight,
+		})
+		.collect()
+}
+
+/// Creates a painter of the given type, configured with the painter options
+fn create_painter(
+	painter_type: &PainterType,
+	options: &Opt,
+	rng_seed: u32,
+) -> Box<dyn Painter + Send + Sync> {
 	// TODO: error out on passed painter options that are unused?
-	match &options.painter[..] {
-		"circles" => {
+	let color_seed = match options.color_mode {
+		ColorMode::Seed => 1.0,
+		_ => options.color_seed,
+	};
+	match painter_type {
+		PainterType::Circles => {
 			let mut painter = CirclePainter::new();
-			painter.options.blending_mode = options.blending_mode;
-			painter.options.alpha = options.painter_alpha;
+			painter.options.blending_mode = options.blending_mode.clone();
+			painter.options.fill = options.painter_fill.clone();
+			painter.options.alpha = options.painter_alpha.clone();
 			painter.options.alpha_bias = options.painter_alpha_bias;
-			painter.options.radius = options.painter_radius;
+			painter.options.radius = options.painter_radius.clone();
 			painter.options.radius_bias = options.painter_radius_bias;
 			painter.options.anti_alias = !options.painter_disable_anti_alias;
-			painter.options.color_seed = options.color_seed;
+			painter.options.color_seed = color_seed;
 			painter.options.rng_seed = rng_seed;
-			painter.options.margins = options.margins;
-			gen.process(
-				options.max_tries,
-				options.generations,
-				options.diff,
-				options.benchmark,
-				candidates,
-				painter,
-				Some(on_processed),
-			);
+			painter.options.margins = options.margins.clone();
+			Box::new(painter)
 		}
-		"rects" => {
+		PainterType::Rects => {
 			let mut painter = RectPainter::new();
-			painter.options.blending_mode = options.blending_mode;
-			painter.options.alpha = options.painter_alpha;
+			painter.options.blending_mode = options.blending_mode.clone();
+			painter.options.fill = options.painter_fill.clone();
+			painter.options.alpha = options.painter_alpha.clone();
 			painter.options.alpha_bias = options.painter_alpha_bias;
-			painter.options.width = options.painter_width;
+			painter.options.width = options.painter_width.clone();
 			painter.options.width_bias = options.painter_width_bias;
-			painter.options.height = options.painter_height;
+			painter.options.height = options.painter_height.clone();
 			painter.options.height_bias = options.painter_height_bias;
-			painter.options.color_seed = options.color_seed;
+			painter.options.rotation = options.painter_rotation.clone();
+			painter.options.rotation_bias = options.painter_rotation_bias;
+			painter.options.anti_alias = !options.painter_disable_anti_alias;
+			painter.options.color_seed = color_seed;
 			painter.options.rng_seed = rng_seed;
-			painter.options.margins = options.margins;
-			gen.process(
-				options.max_tries,
-				options.generations,
-				options.diff,
-				options.benchmark,
-				candidates,
-				painter,
-				Some(on_processed),
-			);
+			painter.options.margins = options.margins.clone();
+			Box::new(painter)
 		}
-		"strokes" => {
+		PainterType::Strokes => {
 			let mut painter = StrokePainter::new();
-			painter.options.blending_mode = options.blending_mode;
-			painter.options.alpha = options.painter_alpha;
+			painter.options.blending_mode = options.blending_mode.clone();
+			painter.options.fill = options.painter_fill.clone();
+			painter.options.alpha = options.painter_alpha.clone();
 			painter.options.alpha_bias = options.painter_alpha_bias;
-			painter.options.width = options.painter_width;
+			painter.options.width = options.painter_width.clone();
 			painter.options.width_bias = options.painter_width_bias;
-			painter.options.height = options.painter_height;
+			painter.options.height = options.painter_height.clone();
 			painter.options.height_bias = options.painter_height_bias;
-			painter.options.wave_height = options.painter_wave_height;
+			painter.options.wave_height = options.painter_wave_height.clone();
 			painter.options.wave_height_bias = options.painter_wave_height_bias;
-			painter.options.wave_length = options.painter_wave_length;
+			painter.options.wave_length = options.painter_wave_length.clone();
 			painter.options.wave_length_bias = options.painter_wave_length_bias;
+			painter.options.rotation = options.painter_rotation.clone();
+			painter.options.rotation_bias = options.painter_rotation_bias;
 			painter.options.anti_alias = !options.painter_disable_anti_alias;
-			painter.options.color_seed = options.color_seed;
+			painter.options.color_seed = color_seed;
 			painter.options.rng_seed = rng_seed;
-			painter.options.margins = options.margins;
-			gen.process(
-				options.max_tries,
-				options.generations,
-				options.diff,
-				options.benchmark,
-				candidates,
-				painter,
-				Some(on_processed),
-			);
+			painter.options.margins = options.margins.clone();
+			Box::new(painter)
+		}
+		PainterType::Triangles => {
+			let mut painter = TrianglePainter::new();
+			painter.options.blending_mode = options.blending_mode.clone();
+			painter.options.fill = options.painter_fill.clone();
+			painter.options.alpha = options.painter_alpha.clone();
+			painter.options.alpha_bias = options.painter_alpha_bias;
+			painter.options.width = options.painter_width.clone();
+			painter.options.width_bias = options.painter_width_bias;
+			painter.options.height = options.painter_height.clone();
+			painter.options.height_bias = options.painter_height_bias;
+			painter.options.anti_alias = !options.painter_disable_anti_alias;
+			painter.options.color_seed = color_seed;
+			painter.options.rng_seed = rng_seed;
+			painter.options.margins = options.margins.clone();
+			Box::new(painter)
+		}
+		PainterType::Polygons => {
+			let mut painter = PolygonPainter::new();
+			painter.options.blending_mode = options.blending_mode.clone();
+			painter.options.fill = options.painter_fill.clone();
+			painter.options.alpha = options.painter_alpha.clone();
+			painter.options.alpha_bias = options.painter_alpha_bias;
+			painter.options.vertices = options.painter_vertices.clone();
+			painter.options.radius = options.painter_radius.clone();
+			painter.options.radius_bias = options.painter_radius_bias;
+			painter.options.irregularity = options.painter_irregularity.clone();
+			painter.options.irregularity_bias = options.painter_irregularity_bias;
+			painter.options.anti_alias = !options.painter_disable_anti_alias;
+			painter.options.color_seed = color_seed;
+			painter.options.rng_seed = rng_seed;
+			painter.options.margins = options.margins.clone();
+			Box::new(painter)
+		}
+		PainterType::Ellipses => {
+			let mut painter = EllipsePainter::new();
+			painter.options.blending_mode = options.blending_mode.clone();
+			painter.options.fill = options.painter_fill.clone();
+			painter.options.alpha = options.painter_alpha.clone();
+			painter.options.alpha_bias = options.painter_alpha_bias;
+			painter.options.radius_x = options.painter_radius_x.clone();
+			painter.options.radius_x_bias = options.painter_radius_x_bias;
+			painter.options.radius_y = options.painter_radius_y.clone();
+			painter.options.radius_y_bias = options.painter_radius_y_bias;
+			painter.options.rotation = options.painter_rotation.clone();
+			painter.options.rotation_bias = options.painter_rotation_bias;
+			painter.options.anti_alias = !options.painter_disable_anti_alias;
+			painter.options.color_seed = color_seed;
+			painter.options.rng_seed = rng_seed;
+			painter.options.margins = options.margins.clone();
+			Box::new(painter)
+		}
+		PainterType::Brushes => {
+			let mut painter = BrushPainter::new();
+			painter.options.blending_mode = options.blending_mode.clone();
+			painter.options.fill = options.painter_fill.clone();
+			painter.options.alpha = options.painter_alpha.clone();
+			painter.options.alpha_bias = options.painter_alpha_bias;
+			painter.options.thickness = options.painter_thickness.clone();
+			painter.options.thickness_bias = options.painter_thickness_bias;
+			painter.options.length = options.painter_length.clone();
+			painter.options.length_bias = options.painter_length_bias;
+			painter.options.taper = options.painter_taper.clone();
+			painter.options.taper_bias = options.painter_taper_bias;
+			painter.options.curvature = options.painter_curvature.clone();
+			painter.options.curvature_bias = options.painter_curvature_bias;
+			painter.options.follow_edges = options.painter_follow_edges;
+			painter.options.anti_alias = !options.painter_disable_anti_alias;
+			painter.options.color_seed = color_seed;
+			painter.options.rng_seed = rng_seed;
+			painter.options.margins = options.margins.clone();
+			Box::new(painter)
+		}
+		PainterType::Lines => {
+			let mut painter = LinePainter::new();
+			painter.options.blending_mode = options.blending_mode.clone();
+			painter.options.fill = options.painter_fill.clone();
+			painter.options.alpha = options.painter_alpha.clone();
+			painter.options.alpha_bias = options.painter_alpha_bias;
+			painter.options.thickness = options.painter_thickness.clone();
+			painter.options.thickness_bias = options.painter_thickness_bias;
+			painter.options.length = options.painter_length.clone();
+			painter.options.length_bias = options.painter_length_bias;
+			painter.options.angle = options.painter_angle.clone();
+			painter.options.angle_bias = options.painter_angle_bias;
+			painter.options.anti_alias = !options.painter_disable_anti_alias;
+			painter.options.color_seed = color_seed;
+			painter.options.rng_seed = rng_seed;
+			painter.options.margins = options.margins.clone();
+			Box::new(painter)
+		}
+		PainterType::Stamps => {
+			if options.painter_stamp.is_empty() {
+				panic!("Stamp files are required when painting stamps, exiting");
+			}
+
+			let mut painter = StampPainter::new();
+			painter.options.blending_mode = options.blending_mode.clone();
+			painter.options.fill = options.painter_fill.clone();
+			painter.options.alpha = options.painter_alpha.clone();
+			painter.options.alpha_bias = options.painter_alpha_bias;
+			painter.options.stamps = options
+				.painter_stamp
+				.iter()
+				.map(|stamp| {
+					let stamp_file = stamp.value.as_path();
+					let stamp_image = image::open(stamp_file).unwrap_or_else(|err| {
+						panic!("Cannot open stamp file {:?}: {}, exiting", stamp_file, err)
+					});
+					println!(
+						"Using stamp image of {:?} with dimensions of {:?}.",
+						stamp_file,
+						stamp_image.dimensions()
+					);
+					WeightedValue {
+						value: to_alpha_mask(&stamp_image),
+						weight: stamp.weight,
+					}
+				})
+				.collect();
+			painter.options.scale = options.painter_scale.clone();
+			painter.options.scale_bias = options.painter_scale_bias;
+			painter.options.rotation = options.painter_rotation.clone();
+			painter.options.rotation_bias = options.painter_rotation_bias;
+			painter.options.color_seed = color_seed;
+			painter.options.rng_seed = rng_seed;
+			painter.options.margins = options.margins.clone();
+			Box::new(painter)
+		}
+		PainterType::Glyphs => {
+			let font_path =
+				options.painter_font.as_ref().expect("Font file is required when painting glyphs, exiting");
+			let font_file = font_path.as_path();
+			let font_data = fs::read(font_file)
+				.unwrap_or_else(|err| panic!("Cannot open font file {:?}: {}, exiting", font_file, err));
+			let font = FontArc::try_from_vec(font_data)
+				.unwrap_or_else(|err| panic!("Cannot parse font file {:?}: {}, exiting", font_file, err));
+			let characters = GlyphPainter::get_paintable_characters(&font, &options.painter_characters);
+			if characters.is_empty() {
+				panic!("None of the characters can be painted with the font file {:?}, exiting", font_file);
+			}
+			println!("Using font file of {:?} with {} paintable characters.", font_file, characters.len());
+
+			let mut painter = GlyphPainter::new();
+			painter.options.blending_mode = options.blending_mode.clone();
+			painter.options.fill = options.painter_fill.clone();
+			painter.options.alpha = options.painter_alpha.clone();
+			painter.options.alpha_bias = options.painter_alpha_bias;
+			painter.options.font = Some(font);
+			painter.options.characters = characters;
+			painter.options.font_size = options.painter_font_size.clone();
+			painter.options.font_size_bias = options.painter_font_size_bias;
+			painter.options.rotation = options.painter_rotation.clone();
+			painter.options.rotation_bias = options.painter_rotation_bias;
+			painter.options.color_seed = color_seed;
+			painter.options.rng_seed = rng_seed;
+			painter.options.margins = options.margins.clone();
+			Box::new(painter)
 		}
-		_ => unreachable!(),
 	}
 }